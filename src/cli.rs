@@ -0,0 +1,241 @@
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+use crate::{
+    components::{Node, NodeType, ReviewDecision, Severity, Workflow, has_errors},
+    executor::{ExecutionEvent, WorkflowExecutor},
+    file_manager
+};
+
+const USAGE: &str = "Usage: mosaik run <workflow.json> [--set <node>=<value>]... [--stdout] [--approve]
+
+Runs a saved workflow without opening the window.
+
+Options:
+  --set <node>=<value>  Replace the text of a Prompt node, <node> is its title or id
+  --stdout              Print the final outputs even when the workflow has File Export nodes
+  --approve             Approve every Review node instead of asking";
+
+struct RunArgs {
+    workflow_path: PathBuf,
+    overrides: Vec<(String, String)>,
+    stdout: bool,
+    approve: bool,
+}
+
+/// Entry point of `mosaik run`, returns the process exit code
+pub fn run(args: &[String]) -> i32 {
+    match run_workflow_file(args) {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        Err(e) => {
+            eprintln!("error: {:#}", e);
+            2
+        }
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<RunArgs> {
+    let mut workflow_path = None;
+    let mut overrides = Vec::new();
+    let mut stdout = false;
+    let mut approve = false;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--set" => {
+                let assignment = args.next().context("--set needs a <node>=<value> argument")?;
+                let (node, value) = assignment.split_once('=')
+                    .with_context(|| format!("Expected <node>=<value>, got \"{}\"", assignment))?;
+                overrides.push((node.to_string(), value.to_string()));
+            }
+            "--stdout" => stdout = true,
+            "--approve" => approve = true,
+            "-h" | "--help" => anyhow::bail!("{}", USAGE),
+            _ if arg.starts_with("--") => anyhow::bail!("Unknown option {}\n\n{}", arg, USAGE),
+            _ if workflow_path.is_none() => workflow_path = Some(PathBuf::from(arg)),
+            _ => anyhow::bail!("Unexpected argument {}\n\n{}", arg, USAGE),
+        }
+    }
+
+    Ok(RunArgs {
+        workflow_path: workflow_path.with_context(|| format!("Missing workflow file\n\n{}", USAGE))?,
+        overrides,
+        stdout,
+        approve,
+    })
+}
+
+/// Find a node by id or by its (unique) title
+fn find_node(workflow: &Workflow, name: &str) -> Result<usize> {
+    if let Ok(id) = name.parse::<usize>() {
+        if workflow.nodes.contains_key(&id) {
+            return Ok(id);
+        }
+    }
+    let matches: Vec<usize> = workflow.nodes
+        .values()
+        .filter(|node| node.title == name)
+        .map(|node| node.id)
+        .collect();
+    match matches.as_slice() {
+        [id] => Ok(*id),
+        [] => anyhow::bail!("No node named \"{}\"", name),
+        _ => anyhow::bail!("Several nodes are named \"{}\", use the node id instead", name),
+    }
+}
+
+/// Run the workflow, returns whether every node succeeded
+fn run_workflow_file(args: &[String]) -> Result<bool> {
+    let args = parse_args(args)?;
+    let mut workflow = file_manager::load_workflow(&args.workflow_path)?;
+
+    for (name, value) in &args.overrides {
+        let node_id = find_node(&workflow, name)?;
+        let Some(NodeType::Prompt {}) = workflow.nodes.get(&node_id).map(|n| &n.node_type) else {
+            anyhow::bail!("Node \"{}\" is not a Prompt node", name);
+        };
+        workflow.update_node_output(node_id, value.clone());
+    }
+
+    // Answers and reviews from the canvas are saved with the workflow, every scheduled run starts fresh
+    for node in workflow.nodes.values_mut() {
+        match node.node_type {
+            NodeType::Model { .. } | NodeType::Review { .. } => node.reset(),
+            NodeType::FileExport { .. } => node.needs_execution = true,
+            _ => {}
+        }
+    }
+
+    let issues = workflow.validate();
+    for issue in &issues {
+        let label = if issue.severity == Severity::Error { "error" } else { "warning" };
+        eprintln!("{}: {}", label, issue.message);
+    }
+    if has_errors(&issues) {
+        anyhow::bail!("The workflow is not valid");
+    }
+
+    let titles: HashMap<usize, String> = workflow.nodes
+        .values()
+        .map(|node| (node.id, node.title.clone()))
+        .collect();
+    let (events_tx, mut events_rx) = tokio::sync::mpsc::unbounded_channel();
+    let provider_config = file_manager::load_provider_config().unwrap_or_default();
+    let mut executor = WorkflowExecutor::new(workflow, events_tx)
+        .with_provider_config(&provider_config);
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .context("Failed to start the async runtime")?;
+    let approve = args.approve;
+    let workflow = runtime.block_on(async move {
+        let report = async {
+            while let Some(event) = events_rx.recv().await {
+                report_progress(&titles, &event);
+                if let ExecutionEvent::ReviewRequested { node_id, input, reply, .. } = event {
+                    let title = titles.get(&node_id).cloned().unwrap_or_else(|| "?".to_string());
+                    // Off the runtime's only thread, other generations keep streaming while waiting
+                    let decision = if approve {
+                        ReviewDecision::Approve(input)
+                    } else {
+                        tokio::task::spawn_blocking(move || ask_review(&title, input))
+                            .await
+                            .unwrap_or(ReviewDecision::Reject)
+                    };
+                    let _ = reply.send(decision);
+                }
+            }
+        };
+        let run = async {
+            executor.run().await;
+            executor.into_workflow()
+        };
+        tokio::join!(report, run).1
+    });
+
+    // File Export nodes wrote their files during the run
+    let succeeded = workflow.nodes.values().all(|node| node.error.is_none());
+    let exported = workflow.nodes.values().any(|node| matches!(
+        &node.node_type,
+        NodeType::FileExport { saved_path: Some(_), .. }
+    ));
+
+    if args.stdout || !exported {
+        // Final outputs are the answers of model nodes that don't feed another node, the last
+        // answer fed back into a loop counts as final too
+        let feeds_another_node = |node: &Node| workflow.connections.values().any(|conn| {
+            conn.from_node_id == node.id && !conn.is_loop_back() && !matches!(
+                workflow.nodes.get(&conn.to_node_id).map(|n| &n.node_type),
+                Some(NodeType::FileExport { .. })
+            )
+        });
+        let outputs: Vec<&str> = sorted_nodes(&workflow)
+            .into_iter()
+            .filter(|node| matches!(node.node_type, NodeType::Model { .. } | NodeType::Map { .. }) && !feeds_another_node(node))
+            .filter_map(|node| node.output.as_deref())
+            .collect();
+        println!("{}", outputs.join("\n\n"));
+    }
+
+    Ok(succeeded)
+}
+
+/// Nodes in canvas reading order, top-to-bottom then left-to-right
+fn sorted_nodes(workflow: &Workflow) -> Vec<&Node> {
+    let mut nodes: Vec<_> = workflow.nodes.values().collect();
+    nodes.sort_by(|a, b| {
+        a.position_y.partial_cmp(&b.position_y)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.position_x.partial_cmp(&b.position_x).unwrap_or(std::cmp::Ordering::Equal))
+    });
+    nodes
+}
+
+/// Ask on the terminal whether the input of a Review node may go on, anything but yes rejects it
+fn ask_review(title: &str, input: String) -> ReviewDecision {
+    eprintln!("? {} needs a review:\n{}", title, input);
+    eprint!("Approve? [y/N] ");
+    let _ = std::io::stderr().flush();
+    let mut answer = String::new();
+    let approved = std::io::stdin().lock().read_line(&mut answer).is_ok()
+        && matches!(answer.trim().to_lowercase().as_str(), "y" | "yes");
+    if approved {
+        eprintln!("✓ {}: approved", title);
+        ReviewDecision::Approve(input)
+    } else {
+        eprintln!("- {}: rejected", title);
+        ReviewDecision::Reject
+    }
+}
+
+fn report_progress(titles: &HashMap<usize, String>, event: &ExecutionEvent) {
+    let title = |node_id: &usize| titles.get(node_id).map(String::as_str).unwrap_or("?");
+    match event {
+        ExecutionEvent::NodeStarted { node_id, .. } => eprintln!("▸ {}", title(node_id)),
+        ExecutionEvent::NodeFinished { node_id, cached: false, .. } => eprintln!("✓ {}", title(node_id)),
+        ExecutionEvent::NodeFinished { node_id, cached: true, .. } => eprintln!("✓ {} (cached)", title(node_id)),
+        ExecutionEvent::NodeSkipped { node_id } => eprintln!("- {}: skipped", title(node_id)),
+        ExecutionEvent::FileRead { node_id, .. } => eprintln!("↻ {}: file changed", title(node_id)),
+        ExecutionEvent::FolderRead { node_id, listing } => {
+            eprintln!("✓ {}: read {} files", title(node_id), listing.files.len());
+            for left_out in &listing.left_out {
+                eprintln!("  left out {}", left_out);
+            }
+        }
+        ExecutionEvent::FileWritten { node_id, path } => eprintln!("✓ {}: saved {}", title(node_id), path.display()),
+        ExecutionEvent::NodeStopped { node_id } => eprintln!("■ {}: stopped", title(node_id)),
+        ExecutionEvent::LoopIteration { node_id, feed_back: true, .. } => eprintln!("↻ {}: again", title(node_id)),
+        ExecutionEvent::LoopIteration { node_id, feed_back: false, .. } => eprintln!("✓ {}: done", title(node_id)),
+        ExecutionEvent::MapStarted { node_id, .. } => eprintln!("▸ {}", title(node_id)),
+        ExecutionEvent::MapItemFinished { node_id, index, .. } => eprintln!("  {}: item {} done", title(node_id), index + 1),
+        ExecutionEvent::MapFinished { node_id, .. } => eprintln!("✓ {}", title(node_id)),
+        ExecutionEvent::NodeFailed { node_id, error } => {
+            eprintln!("✗ {}: {}: {}", title(node_id), error.kind.label(), error.message);
+        }
+        _ => {}
+    }
+}
@@ -4,6 +4,7 @@ use crate::components::{
     workflow::Workflow,
     nodes::NodeType,
 };
+use crate::file_manager;
 
 // Canvas state
 #[derive(Default, Clone)]
@@ -56,45 +57,39 @@ pub fn Canvas(
     };
     // Mouse event handlers for panning
     let on_mouse_down = move |event: Event<MouseData>| {
-        // Match on the result of trigger_button()
+        // Only the left and right buttons do something on the canvas
         match event.trigger_button() {
-            // --- Case 1: A button was pressed ---
-            Some(button) => {
-                match button {
-                    // --- Subcase 1.1: Right Mouse Button (Secondary) ---
-                    dioxus::html::input_data::MouseButton::Secondary => {
-                        // Get coordinates relative to the element receiving the event
-                        let coords = event.element_coordinates();
-
-                        // Set position for the context menu
-                        context_menu_pos_x.set(coords.x);
-                        context_menu_pos_y.set(coords.y);
-                        
-                        // Make the context menu visible
-                        context_menu_visible.set(true);
+            // --- Case 1: Right Mouse Button (Secondary) ---
+            Some(dioxus::html::input_data::MouseButton::Secondary) => {
+                // Get coordinates relative to the element receiving the event
+                let coords = event.element_coordinates();
 
-                        last_right_click_page_pos.set((coords.x, coords.y));
-                    }
-                    // --- Subcase 1.2: Left Mouse Button (Primary) ---
-                    dioxus::html::input_data::MouseButton::Primary => {
-                        if context_menu_visible() {
-                            context_menu_visible.set(false);
-                        }
+                // Set position for the context menu
+                context_menu_pos_x.set(coords.x);
+                context_menu_pos_y.set(coords.y);
+                
+                // Make the context menu visible
+                context_menu_visible.set(true);
 
-                        let mut state = canvas_state.write();
-                        state.dragging = true;
-                        let start_coords = event.element_coordinates();
-                        state.drag_start_x = start_coords.x;
-                        state.drag_start_y = start_coords.y;
-                        // Store the offset *before* this drag started
-                        state.last_offset_x = state.offset_x;
-                        state.last_offset_y = state.offset_y;
-                    }
-                    // --- Subcase 1.3: Other Mouse Buttons (Middle, Back, Forward) ---
-                    _ => {}
+                last_right_click_page_pos.set((coords.x, coords.y));
+            }
+            // --- Case 2: Left Mouse Button (Primary) ---
+            Some(dioxus::html::input_data::MouseButton::Primary) => {
+                if context_menu_visible() {
+                    context_menu_visible.set(false);
                 }
+
+                let mut state = canvas_state.write();
+                state.dragging = true;
+                let start_coords = event.element_coordinates();
+                state.drag_start_x = start_coords.x;
+                state.drag_start_y = start_coords.y;
+                // Store the offset *before* this drag started
+                state.last_offset_x = state.offset_x;
+                state.last_offset_y = state.offset_y;
             }
-            None => {}
+            // --- Case 3: Other Mouse Buttons (Middle, Back, Forward) or none ---
+            _ => {}
         }
     };
 
@@ -257,6 +252,8 @@ pub fn CanvasContextMenu(
     let mut show_models_submenu = use_signal(|| false);
     let mut show_file_submenu = use_signal(|| false);
     let mut show_prompt_submenu = use_signal(|| false);
+    let mut show_flow_submenu = use_signal(|| false);
+    let endpoints = use_hook(|| file_manager::load_provider_config().unwrap_or_default().endpoints);
     
     let menu_item_style = "padding: 8px 15px; cursor: pointer; user-select: none; \
         display: flex; align-items: center; justify-content: space-between;";
@@ -293,6 +290,14 @@ pub fn CanvasContextMenu(
         if show_prompt_submenu() { "block" } else { "none" }
     );
 
+    let flow_submenu_style = format!(
+        "position: absolute; top: 96px; left: 100%; background-color: var(--text-primary); \
+        border: 1px solid #ccc; box-shadow: 0 2px 5px rgba(0,0,0,0.2); \
+        z-index: 201; padding: 5px 0; border-radius: 8px; min-width: 170px; \
+        display: {};",
+        if show_flow_submenu() { "block" } else { "none" }
+    );
+
     rsx! {
         div {
             style: "{menu_style}",
@@ -326,6 +331,17 @@ pub fn CanvasContextMenu(
                             "Prompt"
                         }
                     }
+                    div {
+                        style: "{menu_item_style}",
+                        onclick: move |_| {
+                            on_add_node.call(NodeType::Template { template: String::new() });
+                            visible.set(false);
+                        },
+                        span {
+                            style: "color: var(--ui);",
+                            "Template"
+                        }
+                    }
                 }
             }
             // Models menu
@@ -347,7 +363,7 @@ pub fn CanvasContextMenu(
                     div {
                         style: "{menu_item_style}",
                         onclick: move |_| {
-                            on_add_node.call(NodeType::Model { provider: ProviderType::Ollama, model_name: "".to_string(), messages: Vec::new(), thinking: false });
+                            on_add_node.call(NodeType::model(ProviderType::Ollama));
                             visible.set(false);
                         },
                         span {
@@ -358,7 +374,7 @@ pub fn CanvasContextMenu(
                     div {
                         style: "{menu_item_style}",
                         onclick: move |_| {
-                            on_add_node.call(NodeType::Model { provider: ProviderType::Anthropic, model_name: "".to_string(), messages: Vec::new(), thinking: false });
+                            on_add_node.call(NodeType::model(ProviderType::Anthropic));
                             visible.set(false);
                         },
                         span {
@@ -366,6 +382,41 @@ pub fn CanvasContextMenu(
                             "Anthropic"
                         }
                     }
+                    div {
+                        style: "{menu_item_style}",
+                        onclick: move |_| {
+                            on_add_node.call(NodeType::model(ProviderType::OpenAI));
+                            visible.set(false);
+                        },
+                        span {
+                            style: "color: var(--ui);",
+                            "OpenAI"
+                        }
+                    }
+                    div {
+                        style: "{menu_item_style}",
+                        onclick: move |_| {
+                            on_add_node.call(NodeType::model(ProviderType::Google));
+                            visible.set(false);
+                        },
+                        span {
+                            style: "color: var(--ui);",
+                            "Google"
+                        }
+                    }
+                    for endpoint in endpoints {
+                        div {
+                            style: "{menu_item_style}",
+                            onclick: move |_| {
+                                on_add_node.call(NodeType::model(ProviderType::OpenAICompatible { endpoint: endpoint.name.clone() }));
+                                visible.set(false);
+                            },
+                            span {
+                                style: "color: var(--ui);",
+                                "{endpoint.name}"
+                            }
+                        }
+                    }
                 }
             }
             // File menu
@@ -387,7 +438,7 @@ pub fn CanvasContextMenu(
                     div {
                         style: "{menu_item_style}",
                         onclick: move |_| {
-                            on_add_node.call(NodeType::FileImport { file_path: None, file_name: None });
+                            on_add_node.call(NodeType::FileImport { file_path: None, file_name: None, watch: false, modified: None, format: None, flatten: false });
                             visible.set(false);
                         },
                         span {
@@ -398,7 +449,32 @@ pub fn CanvasContextMenu(
                     div {
                         style: "{menu_item_style}",
                         onclick: move |_| {
-                            on_add_node.call(NodeType::FileExport { folder_path: None, file_name: None, file_type: "txt".to_string() });
+                            on_add_node.call(NodeType::FolderImport {
+                                folder_path: None,
+                                include: "**/*.md".to_string(),
+                                exclude: String::new(),
+                                output: Default::default(),
+                                max_size_kb: 1024,
+                                files: Vec::new(),
+                                left_out: Vec::new(),
+                            });
+                            visible.set(false);
+                        },
+                        span {
+                            style: "color: var(--ui);",
+                            "Import Folder"
+                        }
+                    }
+                    div {
+                        style: "{menu_item_style}",
+                        onclick: move |_| {
+                            on_add_node.call(NodeType::FileExport {
+                                folder_path: None,
+                                file_name: None,
+                                file_type: "txt".to_string(),
+                                write_mode: Default::default(),
+                                saved_path: None,
+                            });
                             visible.set(false);
                         },
                         span {
@@ -408,6 +484,82 @@ pub fn CanvasContextMenu(
                     }
                 }
             }
+            // Flow menu
+            div {
+                style: "{menu_item_style}",
+                onmouseenter: move |_| show_flow_submenu.set(true),
+                onmouseleave: move |_| show_flow_submenu.set(false),
+                span { 
+                    style: "color: var(--ui)",
+                    "Flow" 
+                }
+                span {
+                    style: "color: var(--ui);",
+                    "➤"
+                }
+                div {
+                    style: "{flow_submenu_style}",
+                    onmouseenter: move |_| show_flow_submenu.set(true),
+                    div {
+                        style: "{menu_item_style}",
+                        onclick: move |_| {
+                            on_add_node.call(NodeType::Router {});
+                            visible.set(false);
+                        },
+                        span {
+                            style: "color: var(--ui);",
+                            "Router"
+                        }
+                    }
+                    div {
+                        style: "{menu_item_style}",
+                        onclick: move |_| {
+                            on_add_node.call(NodeType::Loop {
+                                max_iterations: 3,
+                                stop_pattern: String::new(),
+                                stop_word: "DONE".to_string(),
+                                iterations: Vec::new(),
+                            });
+                            visible.set(false);
+                        },
+                        span {
+                            style: "color: var(--ui);",
+                            "Loop"
+                        }
+                    }
+                    div {
+                        style: "{menu_item_style}",
+                        onclick: move |_| {
+                            on_add_node.call(NodeType::Map {
+                                split: Default::default(),
+                                delimiter: String::new(),
+                                max_parallel: 4,
+                                results: Vec::new(),
+                                item: None,
+                            });
+                            visible.set(false);
+                        },
+                        span {
+                            style: "color: var(--ui);",
+                            "Map"
+                        }
+                    }
+                    div {
+                        style: "{menu_item_style}",
+                        onclick: move |_| {
+                            on_add_node.call(NodeType::Review {
+                                status: Default::default(),
+                                edited: None,
+                            });
+                            visible.set(false);
+                        },
+                        span {
+                            style: "color: var(--ui);",
+                            "Review"
+                        }
+                    }
+                }
+            }
         }
     }
 }
\ No newline at end of file
@@ -1,22 +1,71 @@
 use dioxus::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use crate::components::nodes::Node;
+use crate::components::nodes::{Node, RouteRule};
+
+pub const INPUT_PORT: &str = "input";
+pub const SYSTEM_PORT: &str = "system";
+/// Ports of a Loop node that take values from later in the workflow
+pub const FEEDBACK_PORT: &str = "feedback";
+pub const JUDGE_PORT: &str = "judge";
+/// Results of a Map node's items come back through this port
+pub const COLLECT_PORT: &str = "collect";
+pub const OUTPUT_PORT: &str = "output";
+/// Second output of a Map node, carrying the item its body works on
+pub const ITEM_PORT: &str = "item";
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Connection {
     pub id: usize,
     pub from_node_id: usize,
+    #[serde(default = "default_output_port")]
+    pub from_port: String,
     pub to_node_id: usize,
+    #[serde(default = "default_port")]
+    pub to_port: String,
+    /// Place among the connections into the same port, their outputs are combined in this order
+    #[serde(default)]
+    pub order: usize,
+    /// Variable a Template node reads this input as, e.g. `{{doc}}`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// When a Router passes its input on through this connection
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub route: Option<RouteRule>,
+}
+
+impl Connection {
+    /// Connections back into a Loop or Map node close the cycle, they are left out when ordering
+    /// nodes and when looking for loops
+    pub fn is_loop_back(&self) -> bool {
+        is_loop_back_port(&self.to_port)
+    }
+}
+
+/// Whether connections into this port lead back into a Loop or Map node
+pub fn is_loop_back_port(port: &str) -> bool {
+    port == FEEDBACK_PORT || port == JUDGE_PORT || port == COLLECT_PORT
+}
+
+fn default_port() -> String {
+    INPUT_PORT.to_string()
+}
+
+fn default_output_port() -> String {
+    OUTPUT_PORT.to_string()
 }
 
 #[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
 pub struct ConnectionDrawingState {
     pub active: bool,
     pub source_node_id: usize,
+    #[serde(default = "default_output_port")]
+    pub source_port: String,
     pub source_port_world_pos: (f64, f64),
     pub current_mouse_world_pos: (f64, f64),
     pub target_node_id: Option<usize>,
+    #[serde(default)]
+    pub target_port: Option<String>,
 }
 
 impl ConnectionDrawingState {
@@ -41,8 +90,8 @@ pub fn ConnectionsRenderer(
         let from_node = nodes.get(&conn.from_node_id)?;
         let to_node = nodes.get(&conn.to_node_id)?;
 
-        let (from_x, from_y) = get_port_world_pos(from_node, "output");
-        let (to_x, to_y) = get_port_world_pos(to_node, "input");
+        let (from_x, from_y) = get_port_world_pos(from_node, &conn.from_port);
+        let (to_x, to_y) = get_port_world_pos(to_node, &conn.to_port);
         
         let path_data = ConnectionDrawingState::build_path(from_x, from_y, to_x, to_y);
 
@@ -86,6 +135,26 @@ pub fn get_port_world_pos(node: &Node, port_type: &str) -> (f64, f64) {
             node.position_x,
             node.position_y + node.height / 2.0,
         ),
+        "system" => (
+            node.position_x,
+            node.position_y + node.height * 0.75,
+        ),
+        "feedback" => (
+            node.position_x,
+            node.position_y + node.height * 0.7,
+        ),
+        "judge" => (
+            node.position_x,
+            node.position_y + node.height * 0.85,
+        ),
+        "collect" => (
+            node.position_x,
+            node.position_y + node.height * 0.75,
+        ),
+        "item" => (
+            node.position_x + node.width,
+            node.position_y + node.height * 0.3,
+        ),
         "output" => (
             node.position_x + node.width,
             node.position_y + node.height / 2.0,
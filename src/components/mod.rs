@@ -3,10 +3,18 @@ mod nodes;
 mod connections;
 mod workflow;
 mod settings;
+mod validation;
 
 pub use {
     canvas::{Canvas, CanvasState},
-    workflow::{WorkflowManager, Workflow},
-    nodes::{ChatMessage, MessageRole, NodeType, model::execute_model_node},
-    settings::SettingsPopup
-};
\ No newline at end of file
+    connections::COLLECT_PORT,
+    workflow::{WorkflowManager, Workflow, RunScope, start_workflow_run},
+    nodes::{ChatMessage, LoopStep, MessageRole, ModelOptions, Node, NodeError, NodeType, ProviderType, FolderListing, ImportedFile, ReviewDecision, read_changed_import, read_folder_import, write_export},
+    settings::SettingsPopup,
+    validation::{Severity, ValidationIssue, ValidationReport, has_errors}
+};
+#[cfg(test)]
+pub use {
+    connections::{FEEDBACK_PORT, INPUT_PORT, ITEM_PORT, OUTPUT_PORT},
+    nodes::{RouteRule, SplitMode}
+};
@@ -0,0 +1,294 @@
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::Path;
+
+/// Extensions offered by the File Import picker
+pub const IMPORT_EXTENSIONS: [&str; 11] = ["txt", "md", "markdown", "pdf", "docx", "html", "htm", "csv", "tsv", "json", "log"];
+
+/// The kind of document a File Import node read, decides how its text is extracted
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentFormat {
+    #[default]
+    Text,
+    Markdown,
+    Pdf,
+    Docx,
+    /// Converted to markdown
+    Html,
+    /// A table, or one entry per row when flattened
+    Csv,
+    /// Pretty printed, or one line per value when flattened
+    Json,
+}
+
+impl DocumentFormat {
+    /// Decide by the extension, files without a known one are recognized by how they start
+    pub fn detect(path: &Path) -> DocumentFormat {
+        let extension = path.extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        match extension.as_str() {
+            "txt" | "text" | "log" => DocumentFormat::Text,
+            "md" | "markdown" => DocumentFormat::Markdown,
+            "pdf" => DocumentFormat::Pdf,
+            "docx" => DocumentFormat::Docx,
+            "html" | "htm" | "xhtml" => DocumentFormat::Html,
+            "csv" | "tsv" => DocumentFormat::Csv,
+            "json" => DocumentFormat::Json,
+            _ => Self::sniff(path),
+        }
+    }
+
+    fn sniff(path: &Path) -> DocumentFormat {
+        let mut head = [0; 512];
+        let read = std::fs::File::open(path).and_then(|mut file| file.read(&mut head)).unwrap_or(0);
+        let head = &head[..read];
+        let text = String::from_utf8_lossy(head).trim_start().to_lowercase();
+        if head.starts_with(b"%PDF") {
+            DocumentFormat::Pdf
+        } else if head.starts_with(b"PK\x03\x04") {
+            DocumentFormat::Docx
+        } else if text.starts_with("<!doctype html") || text.starts_with("<html") {
+            DocumentFormat::Html
+        } else {
+            DocumentFormat::Text
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            DocumentFormat::Text => "Text",
+            DocumentFormat::Markdown => "Markdown",
+            DocumentFormat::Pdf => "PDF",
+            DocumentFormat::Docx => "Word document",
+            DocumentFormat::Html => "HTML",
+            DocumentFormat::Csv => "CSV",
+            DocumentFormat::Json => "JSON",
+        }
+    }
+
+    /// What flattening does to this format, `None` when it can't be flattened
+    pub fn flatten_label(&self) -> Option<&'static str> {
+        match self {
+            DocumentFormat::Csv => Some("One entry per row"),
+            DocumentFormat::Json => Some("One line per value"),
+            _ => None,
+        }
+    }
+
+    /// The text of the file for the nodes after the import
+    pub fn extract(&self, path: &Path, flatten: bool) -> anyhow::Result<String> {
+        let read_text = || std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()));
+        match self {
+            DocumentFormat::Text | DocumentFormat::Markdown => read_text(),
+            DocumentFormat::Pdf => pdf_text(path),
+            DocumentFormat::Docx => docx_text(path),
+            DocumentFormat::Html => Ok(html2md::parse_html(&read_text()?)),
+            DocumentFormat::Csv => {
+                let tab_separated = path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("tsv"));
+                csv_text(&read_text()?, if tab_separated { b'\t' } else { b',' }, flatten)
+                    .with_context(|| format!("{} is not valid CSV", path.display()))
+            }
+            DocumentFormat::Json => {
+                let json: serde_json::Value = serde_json::from_str(&read_text()?)
+                    .with_context(|| format!("{} is not valid JSON", path.display()))?;
+                if flatten {
+                    let mut lines = Vec::new();
+                    flatten_json(&json, String::new(), &mut lines);
+                    Ok(lines.join("\n"))
+                } else {
+                    Ok(serde_json::to_string_pretty(&json)?)
+                }
+            }
+        }
+    }
+}
+
+fn pdf_text(path: &Path) -> anyhow::Result<String> {
+    // The extractor panics on some malformed files instead of returning an error
+    let text = std::panic::catch_unwind(|| pdf_extract::extract_text(path))
+        .map_err(|_| anyhow::anyhow!("Failed to read {}, the PDF seems to be damaged", path.display()))?
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    if text.trim().is_empty() {
+        anyhow::bail!("{} has no text, scanned pages can't be read", path.display());
+    }
+    Ok(text)
+}
+
+/// Paragraphs of a Word document, headings become markdown headings
+fn docx_text(path: &Path) -> anyhow::Result<String> {
+    use quick_xml::events::Event;
+
+    let file = std::fs::File::open(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut archive = zip::ZipArchive::new(file).with_context(|| format!("{} is not a Word document", path.display()))?;
+    let mut xml = String::new();
+    archive.by_name("word/document.xml")
+        .with_context(|| format!("{} is not a Word document", path.display()))?
+        .read_to_string(&mut xml)?;
+
+    let mut reader = quick_xml::Reader::from_str(&xml);
+    let mut paragraphs = Vec::new();
+    let mut paragraph = String::new();
+    let mut heading_level = None;
+    let mut in_text = false;
+    loop {
+        match reader.read_event().with_context(|| format!("{} is damaged", path.display()))? {
+            Event::Start(element) if element.local_name().as_ref() == b"t" => in_text = true,
+            Event::End(element) if element.local_name().as_ref() == b"t" => in_text = false,
+            Event::Text(text) if in_text => paragraph.push_str(&text.unescape()?),
+            Event::Empty(element) => match element.local_name().as_ref() {
+                b"tab" => paragraph.push('\t'),
+                b"br" => paragraph.push('\n'),
+                // Word names its heading styles "Heading1" to "Heading9"
+                b"pStyle" => heading_level = element.try_get_attribute("w:val")?
+                    .and_then(|style| style.unescape_value().ok()?.strip_prefix("Heading")?.parse::<usize>().ok()),
+                _ => {}
+            },
+            Event::End(element) if element.local_name().as_ref() == b"p" => {
+                let text = std::mem::take(&mut paragraph);
+                if !text.trim().is_empty() {
+                    match heading_level.take() {
+                        Some(level) => paragraphs.push(format!("{} {}", "#".repeat(level.clamp(1, 6)), text.trim())),
+                        None => paragraphs.push(text),
+                    }
+                }
+                heading_level = None;
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(paragraphs.join("\n\n"))
+}
+
+/// A markdown table, or every row as `column: value` lines when flattened
+fn csv_text(content: &str, delimiter: u8, flatten: bool) -> anyhow::Result<String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .from_reader(content.as_bytes());
+    let headers: Vec<String> = reader.headers()?.iter().map(str::to_string).collect();
+    let rows = reader.records().collect::<Result<Vec<_>, _>>()?;
+
+    if flatten {
+        let entries: Vec<String> = rows.iter()
+            .map(|row| row.iter()
+                .enumerate()
+                .map(|(index, value)| match headers.get(index) {
+                    Some(header) => format!("{}: {}", header, value),
+                    None => value.to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join("\n"))
+            .collect();
+        return Ok(entries.join("\n\n"));
+    }
+
+    let cell = |value: &str| value.replace('|', "\\|").replace('\n', " ");
+    let line = |cells: Vec<String>| format!("| {} |", cells.join(" | "));
+    let mut table = vec![
+        line(headers.iter().map(|header| cell(header)).collect()),
+        line(vec!["---".to_string(); headers.len()]),
+    ];
+    table.extend(rows.iter().map(|row| line(row.iter().map(cell).collect())));
+    Ok(table.join("\n"))
+}
+
+/// `path: value` lines for every value in the JSON, with the dotted paths Router rules use
+fn flatten_json(value: &serde_json::Value, path: String, lines: &mut Vec<String>) {
+    let child = |key: &str| if path.is_empty() { key.to_string() } else { format!("{}.{}", path, key) };
+    match value {
+        serde_json::Value::Object(map) if !map.is_empty() => {
+            for (key, value) in map {
+                flatten_json(value, child(key), lines);
+            }
+        }
+        serde_json::Value::Array(items) if !items.is_empty() => {
+            for (index, value) in items.iter().enumerate() {
+                flatten_json(value, child(&index.to_string()), lines);
+            }
+        }
+        serde_json::Value::String(text) => lines.push(format!("{}: {}", path, text)),
+        other => lines.push(format!("{}: {}", path, other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::path::PathBuf;
+
+    /// Write `content` to a file of this name in a folder of the test's own
+    fn fixture(test: &str, name: &str, content: &[u8]) -> PathBuf {
+        let folder = std::env::temp_dir().join(format!("mosaik-document-{}-{}", test, std::process::id()));
+        std::fs::create_dir_all(&folder).unwrap();
+        let path = folder.join(name);
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn unknown_extensions_are_sniffed() {
+        assert_eq!(DocumentFormat::detect(Path::new("notes.TSV")), DocumentFormat::Csv);
+        assert_eq!(DocumentFormat::detect(&fixture("sniff", "report", b"%PDF-1.7\n")), DocumentFormat::Pdf);
+        assert_eq!(DocumentFormat::detect(&fixture("sniff", "letter", b"PK\x03\x04rest")), DocumentFormat::Docx);
+        assert_eq!(DocumentFormat::detect(&fixture("sniff", "page", b"\n  <!DOCTYPE html><p>hi</p>")), DocumentFormat::Html);
+        assert_eq!(DocumentFormat::detect(&fixture("sniff", "plain", b"just text")), DocumentFormat::Text);
+        assert_eq!(DocumentFormat::detect(Path::new("missing")), DocumentFormat::Text);
+    }
+
+    #[test]
+    fn docx_paragraphs_and_headings() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main"><w:body>
+<w:p><w:pPr><w:pStyle w:val="Heading1"/></w:pPr><w:r><w:t>Title</w:t></w:r></w:p>
+<w:p><w:r><w:t>Fish &amp; chips</w:t><w:tab/><w:t>cost</w:t></w:r></w:p>
+<w:p></w:p>
+<w:p><w:r><w:t xml:space="preserve">Second </w:t></w:r><w:r><w:t>paragraph</w:t></w:r></w:p>
+</w:body></w:document>"#;
+        let mut docx = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        docx.start_file("word/document.xml", zip::write::SimpleFileOptions::default()).unwrap();
+        docx.write_all(xml.as_bytes()).unwrap();
+        let docx = docx.finish().unwrap().into_inner();
+
+        let path = fixture("docx", "letter.docx", &docx);
+        assert_eq!(DocumentFormat::Docx.extract(&path, false).unwrap(), "# Title\n\nFish & chips\tcost\n\nSecond paragraph");
+        assert!(DocumentFormat::Docx.extract(&fixture("docx", "fake.docx", b"not a zip"), false).is_err());
+    }
+
+    #[test]
+    fn csv_as_table_or_entries() {
+        let content = "name,note\nAda,\"a|b\"\nBob,short,extra\n";
+        assert_eq!(
+            csv_text(content, b',', false).unwrap(),
+            "| name | note |\n| --- | --- |\n| Ada | a\\|b |\n| Bob | short | extra |"
+        );
+        assert_eq!(csv_text(content, b',', true).unwrap(), "name: Ada\nnote: a|b\n\nname: Bob\nnote: short\nextra");
+        assert_eq!(csv_text("a\tb\n1\t2", b'\t', true).unwrap(), "a: 1\nb: 2");
+
+        let path = fixture("csv", "people.tsv", b"a\tb\n1\t2\n");
+        assert_eq!(DocumentFormat::Csv.extract(&path, false).unwrap(), "| a | b |\n| --- | --- |\n| 1 | 2 |");
+    }
+
+    #[test]
+    fn json_pretty_or_flattened() {
+        let path = fixture("json", "data.json", br#"{"user": {"name": "Ada", "tags": ["x", 2]}, "empty": [], "ok": true}"#);
+        assert_eq!(
+            DocumentFormat::Json.extract(&path, true).unwrap(),
+            "empty: []\nok: true\nuser.name: Ada\nuser.tags.0: x\nuser.tags.1: 2"
+        );
+        assert!(DocumentFormat::Json.extract(&path, false).unwrap().starts_with("{\n  \"empty\": [],"));
+        assert!(DocumentFormat::Json.extract(&fixture("json", "broken.json", b"{"), true).is_err());
+    }
+
+    #[test]
+    fn html_becomes_markdown() {
+        let path = fixture("html", "page.html", b"<h1>Title</h1><p>Some <b>bold</b> text</p>");
+        let markdown = DocumentFormat::Html.extract(&path, false).unwrap();
+        assert!(markdown.contains("Title\n=="), "{}", markdown);
+        assert!(markdown.contains("Some **bold** text"), "{}", markdown);
+    }
+}
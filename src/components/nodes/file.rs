@@ -1,55 +1,145 @@
 use dioxus::prelude::*;
-use crate::components::{
-    nodes::{Node, NodeType, NodeSocket},
-    Workflow
+use anyhow::Context;
+use futures::StreamExt;
+use notify::Watcher;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use crate::{
+    components::{
+        nodes::{DocumentFormat, IMPORT_EXTENSIONS, Node, NodeType, NodeSocket},
+        RunScope, Workflow, start_workflow_run
+    },
+    file_manager
 };
 
+/// What a File Export node does when its file already exists
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WriteMode {
+    #[default]
+    Overwrite,
+    /// Add the input to the end of the file
+    Append,
+    /// Write a new file every time, its name ends with the date and time
+    Timestamped,
+}
+
+select_options!(WriteMode {
+    Overwrite => "overwrite", "Overwrite",
+    Append => "append", "Append",
+    Timestamped => "timestamped", "New file each time",
+});
+
+/// Fill the `{{date}}`, `{{time}}` and `{{node.title}}` placeholders of a file name
+pub fn export_file_name(template: &str, node_title: &str, write_mode: WriteMode) -> String {
+    let now = chrono::Local::now();
+    let name = template
+        .replace("{{date}}", &now.format("%Y-%m-%d").to_string())
+        .replace("{{time}}", &now.format("%H-%M-%S").to_string())
+        .replace("{{node.title}}", node_title);
+    // The file has to stay in the export folder, whatever the template or the title contain
+    let mut name = name.replace(['/', '\\'], "-");
+    while name.contains("..") {
+        name = name.replace("..", ".");
+    }
+    match write_mode {
+        WriteMode::Timestamped => format!("{}-{}", name, now.format("%Y%m%d-%H%M%S")),
+        WriteMode::Overwrite | WriteMode::Append => name,
+    }
+}
+
+/// Write the input of a File Export node to its file, returns where it was written
+pub fn write_export(node: &Node) -> anyhow::Result<PathBuf> {
+    let NodeType::FileExport { folder_path, file_name, file_type, write_mode, .. } = &node.node_type else {
+        anyhow::bail!("{} is not a File Export node", node.title);
+    };
+    let (Some(folder), Some(file_name)) = (folder_path, file_name.as_deref().filter(|name| !name.is_empty())) else {
+        anyhow::bail!("No folder or file name set");
+    };
+    let input = node.input.as_deref().context("Nothing to write yet")?;
+    let file_name = export_file_name(file_name, &node.title, *write_mode);
+    file_manager::export_file(folder, &file_name, file_type, input, *write_mode == WriteMode::Append)
+}
+
 #[component]
 pub fn FileImportNode(
     node: Node,
     workflow_state: Signal<Workflow>,
     canvas_zoom: f64,
-    on_start_connection: EventHandler<(usize, Event<MouseData>)>,
-    on_connection_redirect: EventHandler<(usize, Event<MouseData>)>,
+    on_start_connection: EventHandler<(usize, String, Event<MouseData>)>,
+    on_connection_redirect: EventHandler<(usize, String, Event<MouseData>)>,
 ) -> Element {
-    let file_name = use_memo(move || {
+    let (file_name, watch, format, flatten) = use_memo(move || {
         let ws = workflow_state.read();
         if let Some(node) = ws.nodes.get(&node.id) {
-            if let NodeType::FileImport { file_name, .. } = &node.node_type {
-                return file_name.clone();
+            if let NodeType::FileImport { file_name, watch, format, flatten, .. } = &node.node_type {
+                return (file_name.clone(), *watch, *format, *flatten);
+            }
+        }
+        (None, false, None, false)
+    })();
+    let watched_path = use_memo(move || {
+        match workflow_state.read().nodes.get(&node.id).map(|n| &n.node_type) {
+            Some(NodeType::FileImport { file_path: Some(path), watch: true, .. }) => Some(path.clone()),
+            _ => None,
+        }
+    });
+    // A change during a run is picked up once the run is over
+    let mut pending_change = use_signal(|| false);
+    let running = use_memo(move || workflow_state.read().is_running());
+    use_effect(move || {
+        if pending_change() && !running() {
+            pending_change.set(false);
+            spawn(rerun_after_change(workflow_state, node.id));
+        }
+    });
+    // Restarted when the path or the watch setting changes, dropping the old watcher
+    let _watcher = use_resource(move || async move {
+        let Some(path) = watched_path() else { return };
+        if let Err(e) = watch_import_file(workflow_state, pending_change, node.id, Path::new(&path)).await {
+            if let Some(node) = workflow_state.write().nodes.get_mut(&node.id) {
+                node.set_error(&e);
             }
         }
-        None
     });
+
+    let mut update_watch = move |new_watch: bool| {
+        if let Some(NodeType::FileImport { watch, .. }) = workflow_state.write().nodes.get_mut(&node.id).map(|n| &mut n.node_type) {
+            *watch = new_watch;
+        }
+    };
+    // Reads the file again in the new layout
+    let mut update_flatten = move |new_flatten: bool| {
+        if let Some(NodeType::FileImport { flatten, modified, .. }) = workflow_state.write().nodes.get_mut(&node.id).map(|n| &mut n.node_type) {
+            *flatten = new_flatten;
+            *modified = None;
+        }
+        spawn(async move {
+            load_import_file(workflow_state, node.id).await;
+        });
+    };
     let on_file_select = move |_| {
         let mut ws_clone = workflow_state;
         let node_id = node.id;
         
         spawn(async move {
             if let Some(file_path) = rfd::AsyncFileDialog::new()
-                .add_filter("Text Files", &["txt", "md"])
+                .add_filter("Documents", &IMPORT_EXTENSIONS)
                 .pick_file()
                 .await
             {
                 let path_str = file_path.path().to_string_lossy().to_string();
                 let file_name = file_path.file_name();
                 
-                match std::fs::read_to_string(&path_str) {
-                    Ok(content) => {
-                        let mut ws = ws_clone.write();
-                        if let Some(node) = ws.nodes.get_mut(&node_id) {
-                            if let NodeType::FileImport { file_path: fp, file_name: fn_field } = &mut node.node_type {
-                                *fp = Some(path_str);
-                                *fn_field = Some(file_name);
-                                node.output = Some(content);
-                                node.needs_execution = false;
-                            }
-                        }
-                    },
-                    Err(_) => {
-                        // Handle error - could show a message
+                if let Some(node) = ws_clone.write().nodes.get_mut(&node_id) {
+                    if let NodeType::FileImport { file_path: fp, file_name: fn_field, modified, .. } = &mut node.node_type {
+                        *fp = Some(path_str);
+                        *fn_field = Some(file_name);
+                        *modified = None;
                     }
                 }
+                load_import_file(ws_clone, node_id).await;
             }
         });
     };
@@ -66,12 +156,41 @@ pub fn FileImportNode(
         
         div {
             style: "display: flex; flex-direction: column; gap: 6px; height: 100%;",
-            if let Some(name) = file_name() {
+            if let Some(name) = file_name {
                 div {
                     style: "font-size: 12px; color: var(--text-primary); word-break: break-all; 
                             font-weight: bold; padding: 4px 0;",
                     "{name}"
                 }
+                if let Some(format) = format {
+                    div {
+                        style: "font-size: 11px; color: var(--text-secondary);",
+                        "{format.label()}"
+                    }
+                    if let Some(flatten_label) = format.flatten_label() {
+                        label {
+                            style: "display: flex; align-items: center; gap: 6px; font-size: 12px; color: var(--text-primary); cursor: pointer;",
+                            onmousedown: move |event| event.stop_propagation(),
+                            input {
+                                r#type: "checkbox",
+                                checked: flatten,
+                                onchange: move |event| update_flatten(event.checked()),
+                            }
+                            "{flatten_label}"
+                        }
+                    }
+                }
+                label {
+                    style: "display: flex; align-items: center; gap: 6px; font-size: 12px; color: var(--text-primary); cursor: pointer;",
+                    title: "Run the nodes after this one again whenever the file changes",
+                    onmousedown: move |event| event.stop_propagation(),
+                    input {
+                        r#type: "checkbox",
+                        checked: watch,
+                        onchange: move |event| update_watch(event.checked()),
+                    }
+                    "Watch for changes"
+                }
             } else {
                 button {
                     style: "background-color: var(--bg-alt); color: var(--text-primary); border: none; 
@@ -96,77 +215,148 @@ pub fn FileImportNode(
     }
 }
 
+/// The text a File Import node got out of its file
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportedFile {
+    pub content: String,
+    pub modified: SystemTime,
+    pub format: DocumentFormat,
+}
+
+/// The content of a File Import node's file when it was modified since it was last read,
+/// `None` while it is unchanged
+pub fn read_changed_import(node: &Node) -> anyhow::Result<Option<ImportedFile>> {
+    let NodeType::FileImport { file_path: Some(file_path), modified, flatten, .. } = &node.node_type else { return Ok(None) };
+    let last_modified = std::fs::metadata(file_path)
+        .and_then(|metadata| metadata.modified())
+        .with_context(|| format!("Failed to read {}", file_path))?;
+    if node.output.is_some() && *modified == Some(last_modified) {
+        return Ok(None);
+    }
+    let path = Path::new(file_path);
+    let format = DocumentFormat::detect(path);
+    let content = format.extract(path, *flatten)?;
+    Ok(Some(ImportedFile { content, modified: last_modified, format }))
+}
+
+/// Read the selected file into the node's output when it changed, a failed read is shown on
+/// the node. Returns whether the output changed
+pub async fn load_import_file(mut workflow_state: Signal<Workflow>, node_id: usize) -> bool {
+    let Some(node) = workflow_state.read().nodes.get(&node_id).cloned() else { return false };
+    // Extracting a large PDF takes a while, the window stays responsive meanwhile
+    let read = tokio::task::spawn_blocking(move || read_changed_import(&node))
+        .await
+        .map_err(anyhow::Error::from)
+        .and_then(|read| read);
+
+    let mut ws = workflow_state.write();
+    match read {
+        Ok(Some(file)) => ws.finish_import(node_id, file),
+        Ok(None) => false,
+        Err(e) => {
+            if let Some(node) = ws.nodes.get_mut(&node_id) {
+                node.set_error(&e);
+            }
+            false
+        }
+    }
+}
+
+/// Read a changed file and run the nodes after its File Import node
+async fn rerun_after_change(workflow_state: Signal<Workflow>, node_id: usize) {
+    if load_import_file(workflow_state, node_id).await {
+        start_workflow_run(workflow_state, RunScope::Downstream(node_id));
+    }
+}
+
+/// Re-run the nodes after a File Import node whenever its file changes, until the returned
+/// future is dropped. Changes during a run set `pending_change` instead
+async fn watch_import_file(
+    workflow_state: Signal<Workflow>,
+    mut pending_change: Signal<bool>,
+    node_id: usize,
+    path: &Path,
+) -> anyhow::Result<()> {
+    // Editors often replace a file instead of writing to it, so the folder is watched
+    let folder = path.parent().context("The file is not in a folder")?;
+    let file = path.to_path_buf();
+    let (changes_tx, mut changes) = futures::channel::mpsc::unbounded();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if event.is_ok_and(|event| event.paths.contains(&file)) {
+            let _ = changes_tx.unbounded_send(());
+        }
+    })?;
+    watcher.watch(folder, notify::RecursiveMode::NonRecursive)
+        .with_context(|| format!("Failed to watch {}", folder.display()))?;
+
+    while changes.next().await.is_some() {
+        if workflow_state.read().is_running() {
+            pending_change.set(true);
+        } else {
+            rerun_after_change(workflow_state, node_id).await;
+        }
+    }
+    Ok(())
+}
+
 #[component]
 pub fn FileExportNode(
     node: Node,
     workflow_state: Signal<Workflow>,
     canvas_zoom: f64,
-    on_connection_redirect: EventHandler<(usize, Event<MouseData>)>,
+    on_connection_redirect: EventHandler<(usize, String, Event<MouseData>)>,
 ) -> Element {
     let mut error_message = use_signal(|| None::<String>);
-    let (folder_path, file_name, file_type) = use_memo(move || {
+    let (folder_path, file_name, file_type, write_mode, saved_path) = use_memo(move || {
         let ws = workflow_state.read();
         if let Some(node) = ws.nodes.get(&node.id) {
-            if let NodeType::FileExport { folder_path, file_name, file_type } = &node.node_type {
-                return (folder_path.clone(), file_name.clone(), file_type.clone());
+            if let NodeType::FileExport { folder_path, file_name, file_type, write_mode, saved_path } = &node.node_type {
+                return (folder_path.clone(), file_name.clone(), file_type.clone(), *write_mode, saved_path.clone());
             }
         }
-        (None, None, "txt".to_string())
+        (None, None, "txt".to_string(), WriteMode::default(), None)
     })();
 
     let mut update_folder = move |new_folder: String| {
-        if let Some(node) = workflow_state.write().nodes.get_mut(&node.id) {
-            if let NodeType::FileExport { folder_path, .. } = &mut node.node_type {
-                *folder_path = if new_folder.is_empty() { None } else { Some(new_folder) };
-                node.needs_execution = true;
-            }
+        let mut ws = workflow_state.write();
+        if let Some(NodeType::FileExport { folder_path, .. }) = ws.nodes.get_mut(&node.id).map(|n| &mut n.node_type) {
+            *folder_path = if new_folder.is_empty() { None } else { Some(new_folder) };
+            ws.mark_dirty(node.id);
         }
     };
 
     let mut update_filename = move |new_filename: String| {
-        if let Some(node) = workflow_state.write().nodes.get_mut(&node.id) {
-            if let NodeType::FileExport { file_name, .. } = &mut node.node_type {
-                *file_name = if new_filename.is_empty() { None } else { Some(new_filename) };
-                node.needs_execution = true;
-            }
+        let mut ws = workflow_state.write();
+        if let Some(NodeType::FileExport { file_name, .. }) = ws.nodes.get_mut(&node.id).map(|n| &mut n.node_type) {
+            *file_name = if new_filename.is_empty() { None } else { Some(new_filename) };
+            ws.mark_dirty(node.id);
         }
     };
 
     let mut update_file_type = move |new_type: String| {
-        if let Some(node) = workflow_state.write().nodes.get_mut(&node.id) {
-            if let NodeType::FileExport { file_type, .. } = &mut node.node_type {
-                *file_type = new_type;
-                node.needs_execution = true;
-            }
+        let mut ws = workflow_state.write();
+        if let Some(NodeType::FileExport { file_type, .. }) = ws.nodes.get_mut(&node.id).map(|n| &mut n.node_type) {
+            *file_type = new_type;
+            ws.mark_dirty(node.id);
+        }
+    };
+
+    let mut update_write_mode = move |new_mode: WriteMode| {
+        let mut ws = workflow_state.write();
+        if let Some(NodeType::FileExport { write_mode, .. }) = ws.nodes.get_mut(&node.id).map(|n| &mut n.node_type) {
+            *write_mode = new_mode;
+            ws.mark_dirty(node.id);
         }
     };
     
     let save_file = move |_| {
         error_message.set(None);
-        let ws = workflow_state.read();
-        let Some(node) = ws.nodes.get(&node.id) else { return };
-        let NodeType::FileExport { folder_path, file_name, file_type } = &node.node_type else { return };
-        let (Some(input), Some(folder), Some(filename)) = (&node.input, folder_path, file_name) else {
-            error_message.set(Some("Forgot to add folder or filename?".to_string()));
-            return
-        };
-        
-        if filename.is_empty() { 
-            error_message.set(Some("Forgot to add filename?".to_string()));
-            return
-        };
-        let full_filename = format!("{}.{}", filename, file_type);
-        let file_path = std::path::Path::new(folder).join(&full_filename);
-        let input_content = input.clone();
-        
-        spawn(async move {
-            match std::fs::write(&file_path, input_content) {
-                Ok(_) => println!("File saved successfully to: {:?}", file_path),
-                Err(e) => {
-                    error_message.set(Some(format!("Failed to save file: {}", e)));
-                }
-            }
-        });
+        let mut ws = workflow_state.write();
+        match ws.nodes.get(&node.id).map(write_export) {
+            Some(Ok(path)) => ws.finish_export(node.id, path),
+            Some(Err(e)) => error_message.set(Some(format!("{:#}", e))),
+            None => {}
+        }
     };
     
     let choose_folder = move |_| {
@@ -214,7 +404,8 @@ pub fn FileExportNode(
                 input {
                     r#type: "text",
                     value: file_name.unwrap_or_default(),
-                    placeholder: "filename...",
+                    placeholder: "{{{{date}}}}-{{{{node.title}}}}",
+                    title: "{{{{date}}}}, {{{{time}}}} and {{{{node.title}}}} are filled in when the file is written",
                     oninput: move |event| update_filename(event.value()),
                     onmousedown: move |event| event.stop_propagation(),
                     style: "padding: 8px; border: none; border-radius: 4px; background-color: var(--bg-alt); color: var(--text-primary); width: 60%; box-sizing: border-box;"
@@ -238,6 +429,28 @@ pub fn FileExportNode(
                     }
                 }
             }
+
+            select {
+                value: write_mode.key(),
+                onchange: move |event| {
+                    if let Some(mode) = WriteMode::from_key(&event.value()) {
+                        update_write_mode(mode);
+                    }
+                },
+                onmousedown: move |event| event.stop_propagation(),
+                style: "background: var(--bg-alt); border: none; border-radius: 4px; color: var(--text-primary);
+                        padding: 6px 12px; cursor: pointer; box-sizing: border-box;",
+                for (_, key, label) in WriteMode::ALL {
+                    option { value: key, "{label}" }
+                }
+            }
+
+            if let Some(path) = saved_path {
+                div {
+                    style: "font-size: 12px; color: var(--text-success); word-break: break-all;",
+                    "Saved {path.display()}"
+                }
+            }
     
             if let Some(input) = &node.input {
                 if let Some(error) = error_message() {
@@ -265,4 +478,55 @@ pub fn FileExportNode(
             }
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_names_fill_placeholders_and_stay_in_the_folder() {
+        let date = chrono::Local::now().format("%Y-%m-%d").to_string();
+        assert_eq!(export_file_name("{{node.title}} {{date}}", "Summary", WriteMode::Overwrite), format!("Summary {}", date));
+        assert_eq!(export_file_name("{{node.title}}", "a/b\\c", WriteMode::Append), "a-b-c");
+        assert_eq!(export_file_name("../../{{node.title}}", "..", WriteMode::Overwrite), ".-.-.");
+        assert!(!export_file_name("{{time}}", "", WriteMode::Overwrite).contains(':'));
+
+        let timestamped = export_file_name("report", "", WriteMode::Timestamped);
+        assert!(timestamped.starts_with("report-"));
+        assert_eq!(timestamped.len(), "report-20250101-120000".len());
+    }
+
+    #[test]
+    fn append_adds_a_blank_line_before_the_input() {
+        let folder = std::env::temp_dir().join(format!("mosaik-export-{}", std::process::id()));
+        std::fs::create_dir_all(&folder).unwrap();
+        let mut node = Node::new(0, &NodeType::FileExport {
+            folder_path: None,
+            file_name: None,
+            file_type: String::new(),
+            write_mode: WriteMode::default(),
+            saved_path: None,
+        }, 0.0, 0.0);
+        node.node_type = NodeType::FileExport {
+            folder_path: Some(folder.to_string_lossy().to_string()),
+            file_name: Some("{{node.title}}".to_string()),
+            file_type: "md".to_string(),
+            write_mode: WriteMode::Append,
+            saved_path: None,
+        };
+        node.title = "notes".to_string();
+
+        for input in ["first", "second"] {
+            node.input = Some(input.to_string());
+            assert_eq!(write_export(&node).unwrap(), folder.join("notes.md"));
+        }
+        assert_eq!(std::fs::read_to_string(folder.join("notes.md")).unwrap(), "first\n\nsecond");
+
+        if let NodeType::FileExport { write_mode, .. } = &mut node.node_type {
+            *write_mode = WriteMode::Overwrite;
+        }
+        write_export(&node).unwrap();
+        assert_eq!(std::fs::read_to_string(folder.join("notes.md")).unwrap(), "second");
+        std::fs::remove_dir_all(&folder).unwrap();
+    }
+}
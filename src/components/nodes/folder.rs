@@ -0,0 +1,363 @@
+use dioxus::prelude::*;
+use anyhow::Context;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use crate::components::{
+    nodes::{DocumentFormat, Node, NodeType, NodeSocket},
+    Workflow
+};
+
+pub fn default_include() -> String {
+    "**/*.md".to_string()
+}
+
+pub fn default_max_size_kb() -> usize {
+    1024
+}
+
+/// What a Folder Import node passes on
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FolderOutput {
+    /// All files in one text, each starting with its path
+    #[default]
+    Combined,
+    /// A JSON array with one entry per file, for a Map node to go through
+    Items,
+}
+
+select_options!(FolderOutput {
+    Combined => "combined", "One text",
+    Items => "items", "One item per file",
+});
+
+/// What a Folder Import node got out of its folder
+#[derive(Clone, Debug, PartialEq)]
+pub struct FolderListing {
+    pub content: String,
+    /// Paths of the files read, relative to the folder
+    pub files: Vec<String>,
+    /// Matching files that were not read and why
+    pub left_out: Vec<String>,
+}
+
+/// Globs separated by commas or new lines, `*` stays within a folder and `**` goes into subfolders
+fn glob_set(patterns: &str) -> anyhow::Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns.split([',', '\n']).map(str::trim).filter(|pattern| !pattern.is_empty()) {
+        let glob = GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .build()
+            .with_context(|| format!("Invalid pattern {}", pattern))?;
+        builder.add(glob);
+    }
+    Ok(builder.build()?)
+}
+
+/// Read the files of a Folder Import node's folder that match its rules, in path order
+pub fn read_folder_import(node: &Node) -> anyhow::Result<FolderListing> {
+    let NodeType::FolderImport { folder_path: Some(folder_path), include, exclude, output, max_size_kb, .. } = &node.node_type else {
+        anyhow::bail!("No folder selected");
+    };
+    let folder = Path::new(folder_path);
+    let include_set = glob_set(include)?;
+    let exclude_set = glob_set(exclude)?;
+    let max_size = max_size_kb.saturating_mul(1024);
+
+    let mut files = Vec::new();
+    let mut left_out = Vec::new();
+    let mut texts = Vec::new();
+    let mut total_size = 0;
+    let mut limit_reached = false;
+    for entry in walkdir::WalkDir::new(folder).sort_by_file_name() {
+        let entry = entry.with_context(|| format!("Failed to read {}", folder_path))?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let Ok(relative) = entry.path().strip_prefix(folder) else { continue };
+        if !include_set.is_match(relative) || exclude_set.is_match(relative) {
+            continue;
+        }
+        // The same on every platform, so patterns and headers don't depend on it
+        let name = relative.to_string_lossy().replace('\\', "/");
+
+        // Checked before reading, so files past the limit cost nothing
+        let size = entry.metadata()
+            .with_context(|| format!("Failed to read {}", entry.path().display()))?
+            .len() as usize;
+        if limit_reached || total_size + size > max_size {
+            limit_reached = true;
+            left_out.push(format!("{}: over the size limit", name));
+            continue;
+        }
+        let text = match DocumentFormat::detect(entry.path()).extract(entry.path(), false) {
+            Ok(text) => text,
+            Err(e) => {
+                left_out.push(format!("{}: {:#}", name, e));
+                continue;
+            }
+        };
+        total_size += size;
+        texts.push(format!("--- {} ---\n{}", name, text.trim_end()));
+        files.push(name);
+    }
+
+    if files.is_empty() && left_out.is_empty() {
+        anyhow::bail!("No files in {} match {}", folder_path, include);
+    }
+    let content = match output {
+        FolderOutput::Combined => texts.join("\n\n"),
+        FolderOutput::Items => serde_json::to_string_pretty(&texts)?,
+    };
+    Ok(FolderListing { content, files, left_out })
+}
+
+/// Read the folder into the node's output, a failed read is shown on the node
+pub async fn load_folder_import(mut workflow_state: Signal<Workflow>, node_id: usize) {
+    let Some(node) = workflow_state.read().nodes.get(&node_id).cloned() else { return };
+    let read = tokio::task::spawn_blocking(move || read_folder_import(&node))
+        .await
+        .map_err(anyhow::Error::from)
+        .and_then(|read| read);
+
+    let mut ws = workflow_state.write();
+    match read {
+        Ok(listing) => {
+            ws.finish_folder_import(node_id, listing);
+        }
+        Err(e) => {
+            if let Some(node) = ws.nodes.get_mut(&node_id) {
+                node.set_error(&e);
+            }
+        }
+    }
+}
+
+#[component]
+pub fn FolderImportNode(
+    node: Node,
+    workflow_state: Signal<Workflow>,
+    canvas_zoom: f64,
+    on_start_connection: EventHandler<(usize, String, Event<MouseData>)>,
+    on_connection_redirect: EventHandler<(usize, String, Event<MouseData>)>,
+) -> Element {
+    let mut show_files = use_signal(|| false);
+    let NodeType::FolderImport { folder_path, include, exclude, output, max_size_kb, files, left_out } = node.node_type.clone() else {
+        return rsx! {};
+    };
+
+    // The folder is read again when the next run starts
+    let mut update_include = move |value: String| {
+        let mut ws = workflow_state.write();
+        if let Some(NodeType::FolderImport { include, .. }) = ws.nodes.get_mut(&node.id).map(|n| &mut n.node_type) {
+            *include = value;
+            ws.mark_dirty(node.id);
+        }
+    };
+
+    let mut update_exclude = move |value: String| {
+        let mut ws = workflow_state.write();
+        if let Some(NodeType::FolderImport { exclude, .. }) = ws.nodes.get_mut(&node.id).map(|n| &mut n.node_type) {
+            *exclude = value;
+            ws.mark_dirty(node.id);
+        }
+    };
+
+    let mut update_output = move |value: FolderOutput| {
+        let mut ws = workflow_state.write();
+        if let Some(NodeType::FolderImport { output, .. }) = ws.nodes.get_mut(&node.id).map(|n| &mut n.node_type) {
+            *output = value;
+            ws.mark_dirty(node.id);
+        }
+    };
+
+    let mut update_max_size = move |value: usize| {
+        let mut ws = workflow_state.write();
+        if let Some(NodeType::FolderImport { max_size_kb, .. }) = ws.nodes.get_mut(&node.id).map(|n| &mut n.node_type) {
+            *max_size_kb = value;
+            ws.mark_dirty(node.id);
+        }
+    };
+
+    let choose_folder = move |_| {
+        spawn(async move {
+            let Some(folder) = rfd::AsyncFileDialog::new().pick_folder().await else { return };
+            let Some(path) = folder.path().to_str() else { return };
+            if let Some(NodeType::FolderImport { folder_path, .. }) = workflow_state.write().nodes.get_mut(&node.id).map(|n| &mut n.node_type) {
+                *folder_path = Some(path.to_string());
+            }
+            load_folder_import(workflow_state, node.id).await;
+        });
+    };
+
+    let field_style = "padding: 4px 6px; border: none; border-radius: 4px; background-color: var(--bg-alt);
+                       color: var(--text-primary); font-size: 12px; box-sizing: border-box; min-width: 0;";
+    let left_out_list = left_out.join("\n");
+
+    rsx! {
+        NodeSocket {
+            node_id: node.id,
+            canvas_zoom,
+            on_start_connection,
+            on_connection_redirect,
+            input: false,
+            output: true,
+        }
+
+        div {
+            style: "display: flex; flex-direction: column; gap: 6px; height: 100%; overflow-y: auto;",
+            onwheel: move |event| event.stop_propagation(),
+
+            if let Some(path) = &folder_path {
+                div {
+                    style: "font-size: 12px; color: var(--text-primary); word-break: break-all; font-weight: bold; cursor: pointer;",
+                    title: "Choose another folder",
+                    onmousedown: move |event| event.stop_propagation(),
+                    onclick: choose_folder,
+                    "{path}"
+                }
+            } else {
+                button {
+                    style: "background-color: var(--bg-alt); color: var(--text-primary); border: none;
+                            padding: 8px 12px; border-radius: 4px; cursor: pointer; font-weight: bold;",
+                    onclick: choose_folder,
+                    onmousedown: |evt| evt.stop_propagation(),
+                    "Select Folder"
+                }
+            }
+
+            div {
+                style: "display: grid; grid-template-columns: auto 1fr; gap: 4px 8px; align-items: center; font-size: 12px; color: var(--text-primary);",
+                span { title: "Globs separated by commas, ** also matches files in subfolders", "Include" }
+                input {
+                    r#type: "text",
+                    style: field_style,
+                    placeholder: "docs/**/*.md",
+                    value: "{include}",
+                    onmousedown: move |event| event.stop_propagation(),
+                    oninput: move |event| update_include(event.value()),
+                }
+                span { title: "Files matching these globs are left out", "Exclude" }
+                input {
+                    r#type: "text",
+                    style: field_style,
+                    placeholder: "drafts/**",
+                    value: "{exclude}",
+                    onmousedown: move |event| event.stop_propagation(),
+                    oninput: move |event| update_exclude(event.value()),
+                }
+                span { title: "Reading stops once the files reach this total size", "Limit (KB)" }
+                input {
+                    r#type: "number",
+                    min: "1",
+                    style: field_style,
+                    value: "{max_size_kb}",
+                    onmousedown: move |event| event.stop_propagation(),
+                    oninput: move |event| {
+                        if let Ok(value) = event.value().trim().parse() {
+                            update_max_size(value);
+                        }
+                    },
+                }
+                span { "Output" }
+                select {
+                    value: output.key(),
+                    onchange: move |event| {
+                        if let Some(output) = FolderOutput::from_key(&event.value()) {
+                            update_output(output);
+                        }
+                    },
+                    onmousedown: move |event| event.stop_propagation(),
+                    style: "background: var(--ui); border: none; color: var(--text-primary); font-size: 12px; cursor: pointer;",
+                    for (_, key, label) in FolderOutput::ALL {
+                        option { value: key, "{label}" }
+                    }
+                }
+            }
+
+            if !left_out.is_empty() {
+                div {
+                    style: "color: var(--text-warning); font-size: 11px;",
+                    title: "{left_out_list}",
+                    "{left_out.len()} files left out"
+                }
+            }
+
+            if !files.is_empty() {
+                div {
+                    style: "font-size: 12px; color: var(--text-link); cursor: pointer; user-select: none;",
+                    onmousedown: move |event| event.stop_propagation(),
+                    onclick: move |_| show_files.set(!show_files()),
+                    if show_files() { "▾ " } else { "▸ " }
+                    "{files.len()} files"
+                }
+                if show_files() {
+                    for file in files.iter() {
+                        div {
+                            key: "{file}",
+                            style: "font-size: 12px; color: var(--text-primary); word-break: break-all;",
+                            "{file}"
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn folder_import(folder: &Path, include: &str, exclude: &str, output: FolderOutput, max_size_kb: usize) -> Node {
+        let mut node = Node::new(0, &NodeType::FolderImport {
+            folder_path: None,
+            include: String::new(),
+            exclude: String::new(),
+            output: FolderOutput::default(),
+            max_size_kb: 0,
+            files: Vec::new(),
+            left_out: Vec::new(),
+        }, 0.0, 0.0);
+        node.node_type = NodeType::FolderImport {
+            folder_path: Some(folder.to_string_lossy().to_string()),
+            include: include.to_string(),
+            exclude: exclude.to_string(),
+            output,
+            max_size_kb,
+            files: Vec::new(),
+            left_out: Vec::new(),
+        };
+        node
+    }
+
+    #[test]
+    fn globs_and_the_size_limit_pick_the_files() {
+        let folder = std::env::temp_dir().join(format!("mosaik-folder-{}", std::process::id()));
+        for (name, content) in [
+            ("a.md", "A".to_string()),
+            ("c.txt", "C".to_string()),
+            ("notes/b.md", "B".to_string()),
+            ("notes/draft.md", "draft".to_string()),
+            ("z/large.md", "x".repeat(2048)),
+        ] {
+            let path = folder.join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+
+        let listing = read_folder_import(&folder_import(&folder, "**/*.md, *.txt", "**/draft.md", FolderOutput::Combined, 1)).unwrap();
+        assert_eq!(listing.files, vec!["a.md", "c.txt", "notes/b.md"]);
+        assert_eq!(listing.left_out, vec!["z/large.md: over the size limit"]);
+        assert_eq!(listing.content, "--- a.md ---\nA\n\n--- c.txt ---\nC\n\n--- notes/b.md ---\nB");
+
+        // `*` stays in the folder itself
+        let listing = read_folder_import(&folder_import(&folder, "*.md", "", FolderOutput::Items, 1024)).unwrap();
+        assert_eq!(listing.files, vec!["a.md"]);
+        assert_eq!(listing.content, "[\n  \"--- a.md ---\\nA\"\n]");
+
+        assert!(read_folder_import(&folder_import(&folder, "*.pdf", "", FolderOutput::Combined, 1024)).is_err());
+        std::fs::remove_dir_all(&folder).unwrap();
+    }
+}
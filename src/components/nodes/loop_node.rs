@@ -0,0 +1,191 @@
+use dioxus::prelude::*;
+use crate::components::{
+    nodes::{Node, NodeType, NodeSocket, text::{normalize_answer, regex_matches}},
+    Workflow
+};
+
+pub fn default_max_iterations() -> usize {
+    3
+}
+
+pub fn default_stop_word() -> String {
+    "DONE".to_string()
+}
+
+/// What a Loop node does once the nodes after it produced a new value to feed back
+#[derive(Clone, Debug, PartialEq)]
+pub enum LoopStep {
+    /// Nothing new came back
+    Idle,
+    /// Record the value and send it through the loop again
+    Continue(String),
+    /// Record the value, the loop is done
+    Finish(String),
+}
+
+/// Whether the fed back value or the judge's answer ends the loop, the judge has to answer
+/// with nothing but the stop word, so "not done" or "done, but…" goes on
+pub fn loop_should_stop(stop_pattern: &str, stop_word: &str, feedback: &str, judge: Option<&str>) -> bool {
+    let pattern_matched = !stop_pattern.is_empty() && regex_matches(stop_pattern, feedback);
+    let stop_word = normalize_answer(stop_word);
+    let judge_done = !stop_word.is_empty() && judge.is_some_and(|judge| normalize_answer(judge) == stop_word);
+    pattern_matched || judge_done
+}
+
+#[component]
+pub fn LoopNode(
+    node: Node,
+    workflow_state: Signal<Workflow>,
+    canvas_zoom: f64,
+    on_start_connection: EventHandler<(usize, String, Event<MouseData>)>,
+    on_connection_redirect: EventHandler<(usize, String, Event<MouseData>)>,
+) -> Element {
+    let mut show_iterations = use_signal(|| false);
+    let NodeType::Loop { max_iterations, stop_pattern, stop_word, iterations } = node.node_type.clone() else {
+        return rsx! {};
+    };
+    let invalid_pattern = (!stop_pattern.is_empty())
+        .then(|| regex::Regex::new(&stop_pattern).err())
+        .flatten()
+        .map(|e| e.to_string());
+
+    let on_port_hover = move |port: Option<String>| {
+        let mut ws = workflow_state.write();
+        if ws.drawing_connection_state.active {
+            ws.set_connection_target_port(port);
+        }
+    };
+
+    let mut update_max_iterations = move |value: usize| {
+        let mut ws = workflow_state.write();
+        if let Some(NodeType::Loop { max_iterations, .. }) = ws.nodes.get_mut(&node.id).map(|n| &mut n.node_type) {
+            *max_iterations = value.max(1);
+            ws.mark_dirty(node.id);
+        }
+    };
+
+    let mut update_stop_pattern = move |value: String| {
+        let mut ws = workflow_state.write();
+        if let Some(NodeType::Loop { stop_pattern, .. }) = ws.nodes.get_mut(&node.id).map(|n| &mut n.node_type) {
+            *stop_pattern = value;
+            ws.mark_dirty(node.id);
+        }
+    };
+
+    let mut update_stop_word = move |value: String| {
+        let mut ws = workflow_state.write();
+        if let Some(NodeType::Loop { stop_word, .. }) = ws.nodes.get_mut(&node.id).map(|n| &mut n.node_type) {
+            *stop_word = value;
+            ws.mark_dirty(node.id);
+        }
+    };
+
+    let field_style = "padding: 4px 6px; border: none; border-radius: 4px; background-color: var(--bg-alt);
+                       color: var(--text-primary); font-size: 12px; box-sizing: border-box; min-width: 0;";
+
+    rsx! {
+        NodeSocket {
+            node_id: node.id,
+            canvas_zoom,
+            on_start_connection,
+            on_connection_redirect,
+            input: true,
+            output: true,
+            loop_ports: true,
+            on_port_hover
+        }
+
+        div {
+            style: "display: flex; flex-direction: column; gap: 6px; height: 100%; overflow-y: auto;",
+            onwheel: move |event| event.stop_propagation(),
+
+            div {
+                style: "display: grid; grid-template-columns: auto 1fr; gap: 4px 8px; align-items: center; font-size: 12px; color: var(--text-primary);",
+                span { "Max runs" }
+                input {
+                    r#type: "number",
+                    min: "1",
+                    style: field_style,
+                    value: "{max_iterations}",
+                    onmousedown: move |event| event.stop_propagation(),
+                    oninput: move |event| {
+                        if let Ok(value) = event.value().trim().parse() {
+                            update_max_iterations(value);
+                        }
+                    },
+                }
+                span { title: "Stop once the value fed back matches this regex", "Stop on" }
+                input {
+                    r#type: "text",
+                    style: field_style,
+                    placeholder: "regex",
+                    value: "{stop_pattern}",
+                    onmousedown: move |event| event.stop_propagation(),
+                    oninput: move |event| update_stop_pattern(event.value()),
+                }
+                span { title: "Stop once the node connected to the judge input answers with this word", "Judge says" }
+                input {
+                    r#type: "text",
+                    style: field_style,
+                    value: "{stop_word}",
+                    onmousedown: move |event| event.stop_propagation(),
+                    oninput: move |event| update_stop_word(event.value()),
+                }
+            }
+
+            if let Some(error) = invalid_pattern {
+                div {
+                    style: "color: var(--text-warning); font-size: 11px;",
+                    "{error}"
+                }
+            }
+
+            if !iterations.is_empty() {
+                div {
+                    style: "font-size: 12px; color: var(--text-link); cursor: pointer; user-select: none;",
+                    onmousedown: move |event| event.stop_propagation(),
+                    onclick: move |_| show_iterations.set(!show_iterations()),
+                    if show_iterations() { "▾ " } else { "▸ " }
+                    "{iterations.len()} of {max_iterations} runs"
+                }
+                if show_iterations() {
+                    for (index, iteration) in iterations.iter().enumerate() {
+                        div {
+                            key: "{index}",
+                            style: "font-size: 12px; color: var(--text-primary); padding: 4px; border-radius: 4px;
+                                    background-color: var(--bg-alt); white-space: pre-wrap; word-break: break-word;",
+                            span { style: "font-weight: bold;", "{index + 1}. " }
+                            "{iteration}"
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn judge_has_to_answer_with_the_stop_word_alone() {
+        assert!(loop_should_stop("", "DONE", "draft", Some("DONE")));
+        assert!(loop_should_stop("", "DONE", "draft", Some("  done.\n")));
+        assert!(loop_should_stop("", "done", "draft", Some("**Done!**")));
+        assert!(!loop_should_stop("", "DONE", "draft", Some("not done")));
+        assert!(!loop_should_stop("", "DONE", "draft", Some("Done, but the second paragraph needs work")));
+        assert!(!loop_should_stop("", "DONE", "draft", None));
+        // Without a stop word any answer goes on
+        assert!(!loop_should_stop("", "", "draft", Some("")));
+    }
+
+    #[test]
+    fn stop_pattern_matches_the_fed_back_value() {
+        assert!(loop_should_stop("(?i)final answer", "", "Final answer: 42", None));
+        assert!(!loop_should_stop("(?i)final answer", "", "Still thinking", None));
+        assert!(!loop_should_stop("", "", "anything", None));
+        // Either one is enough
+        assert!(loop_should_stop("^never$", "DONE", "draft", Some("done")));
+    }
+}
@@ -0,0 +1,310 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::components::{
+    nodes::{Node, NodeType, NodeSocket, text::parse_json},
+    Workflow
+};
+
+pub fn default_max_parallel() -> usize {
+    4
+}
+
+/// How a Map node cuts its input into the items it runs its body for
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SplitMode {
+    /// Every non-empty line
+    #[default]
+    Lines,
+    /// The text between occurrences of the node's delimiter
+    Delimiter,
+    /// The elements of a JSON array
+    JsonArray,
+    /// Markdown sections, starting at the highest level heading used in the input
+    Headings,
+}
+
+select_options!(SplitMode {
+    Lines => "lines", "Lines",
+    Delimiter => "delimiter", "Delimiter",
+    JsonArray => "json", "JSON array",
+    Headings => "headings", "Headings",
+});
+
+impl SplitMode {
+    /// The items of the input, blank items are left out
+    pub fn split(&self, input: &str, delimiter: &str) -> Vec<String> {
+        let items: Vec<String> = match self {
+            SplitMode::Lines => input.lines().map(str::to_string).collect(),
+            SplitMode::Delimiter => {
+                let delimiter = unescape(delimiter);
+                if delimiter.is_empty() {
+                    vec![input.to_string()]
+                } else {
+                    input.split(delimiter.as_str()).map(str::to_string).collect()
+                }
+            }
+            SplitMode::JsonArray => parse_json::<Vec<serde_json::Value>>(input)
+                .into_iter()
+                .flatten()
+                .map(|value| match value {
+                    serde_json::Value::String(text) => text,
+                    other => other.to_string(),
+                })
+                .collect(),
+            SplitMode::Headings => markdown_sections(input),
+        };
+        items.into_iter()
+            .map(|item| item.trim().to_string())
+            .filter(|item| !item.is_empty())
+            .collect()
+    }
+
+    /// Put several inputs into one, so the items of all of them come out of `split`
+    pub fn combine(&self, inputs: &[String], delimiter: &str) -> String {
+        match self {
+            SplitMode::Lines => inputs.join("\n"),
+            SplitMode::Delimiter if !delimiter.is_empty() => inputs.join(&unescape(delimiter)),
+            // An input that isn't an array becomes a single item
+            SplitMode::JsonArray => {
+                let items: Vec<serde_json::Value> = inputs.iter()
+                    .flat_map(|input| parse_json::<Vec<serde_json::Value>>(input)
+                        .unwrap_or_else(|| vec![serde_json::Value::String(input.clone())]))
+                    .collect();
+                serde_json::to_string_pretty(&items).unwrap_or_default()
+            }
+            _ => inputs.join("\n\n"),
+        }
+    }
+
+    /// Put the results of the items back together, a JSON array stays one
+    pub fn join(&self, results: &[String]) -> String {
+        match self {
+            SplitMode::JsonArray => serde_json::to_string_pretty(results).unwrap_or_default(),
+            _ => results.join("\n\n"),
+        }
+    }
+}
+
+/// Typing a newline or tab into a single line field isn't possible, so `\n` and `\t` stand for them
+fn unescape(delimiter: &str) -> String {
+    delimiter.replace("\\n", "\n").replace("\\t", "\t")
+}
+
+/// Level of a markdown heading line, e.g. 2 for `## Chapter`
+fn heading_level(line: &str) -> Option<usize> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    ((1..=6).contains(&level) && line[level..].starts_with(' ')).then_some(level)
+}
+
+/// Cut markdown at the headings of the highest level it uses, text before the first one is
+/// a section of its own
+fn markdown_sections(input: &str) -> Vec<String> {
+    let Some(level) = input.lines().filter_map(heading_level).min() else {
+        return vec![input.to_string()];
+    };
+    let mut sections = vec![String::new()];
+    for line in input.lines() {
+        if heading_level(line) == Some(level) {
+            sections.push(String::new());
+        }
+        let section = sections.last_mut().expect("sections start with one entry");
+        section.push_str(line);
+        section.push('\n');
+    }
+    sections
+}
+
+#[component]
+pub fn MapNode(
+    node: Node,
+    workflow_state: Signal<Workflow>,
+    canvas_zoom: f64,
+    on_start_connection: EventHandler<(usize, String, Event<MouseData>)>,
+    on_connection_redirect: EventHandler<(usize, String, Event<MouseData>)>,
+) -> Element {
+    let mut show_results = use_signal(|| false);
+    let NodeType::Map { split, delimiter, max_parallel, results, .. } = node.node_type.clone() else {
+        return rsx! {};
+    };
+    let item_count = split.split(node.input.as_deref().unwrap_or_default(), &delimiter).len();
+
+    let on_port_hover = move |port: Option<String>| {
+        let mut ws = workflow_state.write();
+        if ws.drawing_connection_state.active {
+            ws.set_connection_target_port(port);
+        }
+    };
+
+    let mut update_split = move |value: SplitMode| {
+        let mut ws = workflow_state.write();
+        if let Some(NodeType::Map { split, .. }) = ws.nodes.get_mut(&node.id).map(|n| &mut n.node_type) {
+            *split = value;
+            ws.mark_dirty(node.id);
+        }
+    };
+
+    let mut update_delimiter = move |value: String| {
+        let mut ws = workflow_state.write();
+        if let Some(NodeType::Map { delimiter, .. }) = ws.nodes.get_mut(&node.id).map(|n| &mut n.node_type) {
+            *delimiter = value;
+            ws.mark_dirty(node.id);
+        }
+    };
+
+    // Only changes how fast the items are done, the results stay the same
+    let mut update_max_parallel = move |value: usize| {
+        if let Some(NodeType::Map { max_parallel, .. }) = workflow_state.write().nodes.get_mut(&node.id).map(|n| &mut n.node_type) {
+            *max_parallel = value.max(1);
+        }
+    };
+
+    let field_style = "padding: 4px 6px; border: none; border-radius: 4px; background-color: var(--bg-alt);
+                       color: var(--text-primary); font-size: 12px; box-sizing: border-box; min-width: 0;";
+
+    rsx! {
+        NodeSocket {
+            node_id: node.id,
+            canvas_zoom,
+            on_start_connection,
+            on_connection_redirect,
+            input: true,
+            output: true,
+            map_ports: true,
+            on_port_hover
+        }
+
+        div {
+            style: "display: flex; flex-direction: column; gap: 6px; height: 100%; overflow-y: auto;",
+            onwheel: move |event| event.stop_propagation(),
+
+            div {
+                style: "display: grid; grid-template-columns: auto 1fr; gap: 4px 8px; align-items: center; font-size: 12px; color: var(--text-primary);",
+                span { "Split by" }
+                select {
+                    value: split.key(),
+                    onchange: move |event| {
+                        if let Some(mode) = SplitMode::from_key(&event.value()) {
+                            update_split(mode);
+                        }
+                    },
+                    onmousedown: move |event| event.stop_propagation(),
+                    style: "background: var(--ui); border: none; color: var(--text-primary); font-size: 12px; cursor: pointer;",
+                    for (_, key, label) in SplitMode::ALL {
+                        option { value: key, "{label}" }
+                    }
+                }
+                if split == SplitMode::Delimiter {
+                    span { "Delimiter" }
+                    input {
+                        r#type: "text",
+                        style: field_style,
+                        placeholder: "---",
+                        value: "{delimiter}",
+                        onmousedown: move |event| event.stop_propagation(),
+                        oninput: move |event| update_delimiter(event.value()),
+                    }
+                }
+                span { title: "How many items run at the same time", "Parallel" }
+                input {
+                    r#type: "number",
+                    min: "1",
+                    style: field_style,
+                    value: "{max_parallel}",
+                    onmousedown: move |event| event.stop_propagation(),
+                    oninput: move |event| {
+                        if let Ok(value) = event.value().trim().parse() {
+                            update_max_parallel(value);
+                        }
+                    },
+                }
+            }
+
+            div {
+                style: "font-size: 12px; color: var(--text-secondary);",
+                title: "The item goes out at the upper right, the results come back on the lower left",
+                if node.is_executing {
+                    "{results.len()} of {item_count} items done"
+                } else {
+                    "{item_count} items"
+                }
+            }
+
+            if !results.is_empty() {
+                div {
+                    style: "font-size: 12px; color: var(--text-link); cursor: pointer; user-select: none;",
+                    onmousedown: move |event| event.stop_propagation(),
+                    onclick: move |_| show_results.set(!show_results()),
+                    if show_results() { "▾ " } else { "▸ " }
+                    "{results.len()} results"
+                }
+                if show_results() {
+                    for (index, result) in results.iter().enumerate() {
+                        div {
+                            key: "{index}",
+                            style: "font-size: 12px; color: var(--text-primary); padding: 4px; border-radius: 4px;
+                                    background-color: var(--bg-alt); white-space: pre-wrap; word-break: break-word;",
+                            span { style: "font-weight: bold;", "{index + 1}. " }
+                            "{result}"
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_skip_blank_ones() {
+        assert_eq!(SplitMode::Lines.split("a\n\n  b  \r\n\n", ""), vec!["a", "b"]);
+        assert!(SplitMode::Lines.split("  \n", "").is_empty());
+    }
+
+    #[test]
+    fn delimiter_understands_escapes() {
+        assert_eq!(SplitMode::Delimiter.split("a---b--- ---c", "---"), vec!["a", "b", "c"]);
+        assert_eq!(SplitMode::Delimiter.split("a\tb", "\\t"), vec!["a", "b"]);
+        assert_eq!(SplitMode::Delimiter.split("a\n\nb", "\\n\\n"), vec!["a", "b"]);
+        assert_eq!(SplitMode::Delimiter.split("a,b", ""), vec!["a,b"]);
+    }
+
+    #[test]
+    fn json_array_items() {
+        assert_eq!(SplitMode::JsonArray.split(r#"["one", 2, {"three": 3}, ""]"#, ""), vec!["one", "2", r#"{"three":3}"#]);
+        assert_eq!(SplitMode::JsonArray.split("Here you go:\n```json\n[\"a\", \"b\"]\n```", ""), vec!["a", "b"]);
+        assert_eq!(SplitMode::JsonArray.split(r#"{"items": ["a", "b"]}"#, ""), vec!["a", "b"]);
+        assert!(SplitMode::JsonArray.split("not an array", "").is_empty());
+    }
+
+    #[test]
+    fn headings_split_at_the_highest_level_used() {
+        let input = "Intro\n## One\ntext\n### Detail\n## Two\nmore";
+        assert_eq!(
+            SplitMode::Headings.split(input, ""),
+            vec!["Intro", "## One\ntext\n### Detail", "## Two\nmore"]
+        );
+        // `#hashtag` and `#######` are not headings
+        assert_eq!(SplitMode::Headings.split("#tag\n####### deep", ""), vec!["#tag\n####### deep"]);
+    }
+
+    #[test]
+    fn combined_inputs_keep_all_items() {
+        let inputs = |texts: &[&str]| texts.iter().map(|text| text.to_string()).collect::<Vec<_>>();
+        let split = |mode: SplitMode, texts: &[&str], delimiter: &str| mode.split(&mode.combine(&inputs(texts), delimiter), delimiter);
+        assert_eq!(split(SplitMode::Lines, &["a\nb", "c"], ""), vec!["a", "b", "c"]);
+        assert_eq!(split(SplitMode::Delimiter, &["a;b", "c"], ";"), vec!["a", "b", "c"]);
+        assert_eq!(split(SplitMode::JsonArray, &[r#"["a", "b"]"#, "Sure:\n[\"c\"]", "d"], ""), vec!["a", "b", "c", "d"]);
+        assert_eq!(split(SplitMode::Headings, &["# A\none", "# B\ntwo"], ""), vec!["# A\none", "# B\ntwo"]);
+    }
+
+    #[test]
+    fn join_keeps_json_arrays() {
+        let results = ["a".to_string(), "b".to_string()];
+        assert_eq!(SplitMode::Lines.join(&results), "a\n\nb");
+        assert_eq!(SplitMode::JsonArray.join(&results), "[\n  \"a\",\n  \"b\"\n]");
+    }
+}
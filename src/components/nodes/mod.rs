@@ -1,19 +1,69 @@
 use dioxus::prelude::*;
 use serde::{Serialize, Deserialize};
+use std::path::PathBuf;
+use std::time::SystemTime;
+use crate::llm::ErrorKind;
 use crate::components::{
-    canvas::CanvasState, connections::get_port_world_pos, workflow::Workflow,
+    canvas::CanvasState, connections::{get_port_world_pos, COLLECT_PORT, FEEDBACK_PORT, INPUT_PORT, ITEM_PORT, JUDGE_PORT, OUTPUT_PORT, SYSTEM_PORT}, workflow::{RunScope, Workflow, start_workflow_run},
     nodes::{
-        model::ModelNode,
-        file::{FileImportNode, FileExportNode}
+        model::{ModelNode, retry_model_node},
+        file::{FileImportNode, FileExportNode, load_import_file},
+        folder::{FolderImportNode, default_include, default_max_size_kb, load_folder_import},
+        loop_node::{LoopNode, default_max_iterations, default_stop_word},
+        map::{MapNode, default_max_parallel},
+        review::ReviewNode,
+        router::RouterNode,
+        template::TemplateNode
     }
 };
 
+/// Gives a setting's enum the options of its select: `ALL` with every variant, the key its option
+/// is stored under and the label shown, and `key`/`from_key` to go between variants and keys
+macro_rules! select_options {
+    ($name:ident { $($variant:ident => $key:literal, $label:literal),+ $(,)? }) => {
+        impl $name {
+            const ALL: [($name, &'static str, &'static str); [$($key),+].len()] = [$(($name::$variant, $key, $label)),+];
+
+            fn key(&self) -> &'static str {
+                match self {
+                    $($name::$variant => $key),+
+                }
+            }
+
+            fn from_key(key: &str) -> Option<Self> {
+                match key {
+                    $($key => Some($name::$variant),)+
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
 pub mod model;
+mod document;
 mod file;
+mod folder;
+mod loop_node;
+mod map;
+mod review;
+mod router;
+mod template;
+mod text;
+
+pub use loop_node::{LoopStep, loop_should_stop};
+pub use document::{DocumentFormat, IMPORT_EXTENSIONS};
+pub use file::{ImportedFile, WriteMode, read_changed_import, write_export};
+pub use folder::{FolderListing, FolderOutput, read_folder_import};
+pub use map::SplitMode;
+pub use review::{ReviewDecision, ReviewStatus};
+pub use router::{RouteRule, RouterOutput};
+pub use template::{TemplateInput, render_template, unknown_placeholders};
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum MessageRole {
+    System,
     User,
     Assistant,
 }
@@ -26,33 +76,227 @@ pub struct ChatMessage {
     pub thinking: Option<String>,
 }
 
+impl ChatMessage {
+    /// Append a streamed chunk of the same message
+    pub fn append(&mut self, chunk: &ChatMessage) {
+        if let Some(thinking_chunk) = &chunk.thinking {
+            match &mut self.thinking {
+                Some(existing) => existing.push_str(thinking_chunk),
+                None => self.thinking = Some(thinking_chunk.clone()),
+            }
+        }
+        self.content.push_str(&chunk.content);
+    }
+}
+
+/// Per-node sampling parameters, unset values fall back to the provider defaults
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModelOptions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    #[serde(default, alias = "num_predict", skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stop: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+    /// Context window size, only understood by Ollama
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num_ctx: Option<u32>,
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum ProviderType {
     Ollama,
     Anthropic,
+    OpenAI,
+    Google,
+    OpenAICompatible { endpoint: String },
+}
+
+impl ProviderType {
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            ProviderType::Ollama => "Ollama",
+            ProviderType::Anthropic => "Anthropic",
+            ProviderType::OpenAI => "OpenAI",
+            ProviderType::Google => "Google",
+            ProviderType::OpenAICompatible { .. } => "OpenAI-compatible",
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum NodeType {
     Prompt {}, 
+    /// Text with `{{name}}` placeholders filled from the incoming connections
+    Template {
+        #[serde(default)]
+        template: String,
+    },
+    /// Passes its input on only through the outgoing connections whose rule matches
+    Router {},
+    /// Sends its input through the nodes after it again, each time with the value fed back into
+    /// it, until the iteration limit or a stop condition is reached
+    Loop {
+        #[serde(default = "default_max_iterations")]
+        max_iterations: usize,
+        /// Stop once the fed back value matches this regex, unused when empty
+        #[serde(default)]
+        stop_pattern: String,
+        /// Stop once the node connected to the judge port answers with this word
+        #[serde(default = "default_stop_word")]
+        stop_word: String,
+        /// Every value fed back during the last run
+        #[serde(default)]
+        iterations: Vec<String>,
+    },
+    /// Runs the nodes after its item output once for every item of its input and collects
+    /// what comes back into a list
+    Map {
+        #[serde(default)]
+        split: SplitMode,
+        #[serde(default)]
+        delimiter: String,
+        #[serde(default = "default_max_parallel")]
+        max_parallel: usize,
+        /// What came back for each item during the last run
+        #[serde(default)]
+        results: Vec<String>,
+        /// The item a copy of the workflow is running the body for, only set during a run
+        #[serde(skip)]
+        item: Option<String>,
+    },
+    /// Holds the run until someone approves its input, possibly after editing it, or rejects it
+    Review {
+        #[serde(default)]
+        status: ReviewStatus,
+        /// The reviewer's changes to the input, `None` while it is unchanged
+        #[serde(default)]
+        edited: Option<String>,
+    },
     FileImport {
         file_path: Option<String>,
         file_name: Option<String>,
+        /// Re-run the nodes after it when the file changes
+        #[serde(default)]
+        watch: bool,
+        /// Modification time of the file when it was last read
+        #[serde(default)]
+        modified: Option<SystemTime>,
+        /// Detected when the file was last read
+        #[serde(default)]
+        format: Option<DocumentFormat>,
+        /// CSV rows and JSON values one by one instead of as a table or tree
+        #[serde(default)]
+        flatten: bool,
+    },
+    FolderImport {
+        folder_path: Option<String>,
+        /// Globs of the files to read, relative to the folder
+        #[serde(default = "default_include")]
+        include: String,
+        /// Globs of files to leave out even when they match `include`
+        #[serde(default)]
+        exclude: String,
+        #[serde(default)]
+        output: FolderOutput,
+        /// Reading stops once the files read reach this total size on disk
+        #[serde(default = "default_max_size_kb")]
+        max_size_kb: usize,
+        /// Files read the last time
+        #[serde(default)]
+        files: Vec<String>,
+        /// Matching files that were not read the last time and why
+        #[serde(default)]
+        left_out: Vec<String>,
     },
     FileExport {
         folder_path: Option<String>,
+        /// May contain `{{date}}`, `{{time}}` and `{{node.title}}`
         file_name: Option<String>,
         file_type: String, // "txt" or "md"
+        #[serde(default)]
+        write_mode: WriteMode,
+        /// Where the file was written last, shown on the node
+        #[serde(skip)]
+        saved_path: Option<PathBuf>,
     },
     Model {
         provider: ProviderType,
         model_name: String,
         messages: Vec<ChatMessage>,
-        thinking: bool
+        thinking: bool,
+        #[serde(default)]
+        options: ModelOptions,
+        #[serde(default)]
+        system_prompt: String,
+        /// Always ask the provider instead of reusing a cached response
+        #[serde(default)]
+        bypass_cache: bool,
     },
 }
 
+impl NodeType {
+    /// Ports other nodes can connect into, source nodes like Prompt have none
+    pub fn input_ports(&self) -> &'static [&'static str] {
+        match self {
+            NodeType::Prompt {} | NodeType::FileImport { .. } | NodeType::FolderImport { .. } => &[],
+            NodeType::FileExport { .. } | NodeType::Template { .. } | NodeType::Router {} | NodeType::Review { .. } => &[INPUT_PORT],
+            NodeType::Model { .. } => &[INPUT_PORT, SYSTEM_PORT],
+            NodeType::Loop { .. } => &[INPUT_PORT, FEEDBACK_PORT, JUDGE_PORT],
+            NodeType::Map { .. } => &[INPUT_PORT, COLLECT_PORT],
+        }
+    }
+    
+    /// Merge the outputs connected to one of the node's ports, given in connection order
+    pub fn combine_inputs(&self, port: &str, outputs: Vec<String>) -> Option<String> {
+        if outputs.is_empty() {
+            return None;
+        }
+        match (self, port) {
+            (NodeType::Prompt {} | NodeType::FileImport { .. } | NodeType::FolderImport { .. }, _) => None,
+            // A Map node goes through the items of all its inputs, so they are joined the way its items are split
+            (NodeType::Map { split, delimiter, .. }, INPUT_PORT) => Some(split.combine(&outputs, delimiter)),
+            _ => Some(outputs.join("\n\n")),
+        }
+    }
+    
+    /// A fresh model node for the given provider, `Node::new` fills in the provider defaults
+    pub fn model(provider: ProviderType) -> Self {
+        NodeType::Model {
+            provider,
+            model_name: String::new(),
+            messages: Vec::new(),
+            thinking: false,
+            options: ModelOptions::default(),
+            system_prompt: String::new(),
+            bypass_cache: false,
+        }
+    }
+}
+
+/// Failure shown on the node until it is retried or executed again
+#[derive(Clone, Debug, PartialEq)]
+pub struct NodeError {
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl NodeError {
+    pub fn new(error: &anyhow::Error) -> Self {
+        Self {
+            kind: ErrorKind::of(error),
+            message: format!("{:#}", error),
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Node {
     pub id: usize,
@@ -63,12 +307,19 @@ pub struct Node {
     pub height: f64,
     pub title: String,
     pub input: Option<String>,
+    #[serde(default)]
+    pub system_input: Option<String>,
     pub output: Option<String>, 
     pub drag_offset_x: f64,
     pub drag_offset_y: f64,
     pub is_maximized: bool,
     pub needs_execution: bool,
-    pub is_executing: bool
+    pub is_executing: bool,
+    #[serde(skip)]
+    pub error: Option<NodeError>,
+    /// The last run passed over the node because no route led to it
+    #[serde(skip)]
+    pub skipped: bool,
 }
 
 impl Node {
@@ -80,6 +331,52 @@ impl Node {
                 200.0,
                 NodeType::Prompt {},
             ),
+            NodeType::Template { .. } => (
+                "Template".to_string(),
+                250.0,
+                250.0,
+                NodeType::Template {
+                    template: String::new(),
+                },
+            ),
+            NodeType::Router {} => (
+                "Router".to_string(),
+                260.0,
+                220.0,
+                NodeType::Router {},
+            ),
+            NodeType::Loop { .. } => (
+                "Loop".to_string(),
+                260.0,
+                240.0,
+                NodeType::Loop {
+                    max_iterations: default_max_iterations(),
+                    stop_pattern: String::new(),
+                    stop_word: default_stop_word(),
+                    iterations: Vec::new(),
+                },
+            ),
+            NodeType::Map { .. } => (
+                "Map".to_string(),
+                260.0,
+                240.0,
+                NodeType::Map {
+                    split: SplitMode::default(),
+                    delimiter: String::new(),
+                    max_parallel: default_max_parallel(),
+                    results: Vec::new(),
+                    item: None,
+                },
+            ),
+            NodeType::Review { .. } => (
+                "Review".to_string(),
+                280.0,
+                260.0,
+                NodeType::Review {
+                    status: ReviewStatus::default(),
+                    edited: None,
+                },
+            ),
             NodeType::FileImport { .. } => (
                 "File Import".to_string(),
                 200.0,
@@ -87,22 +384,48 @@ impl Node {
                 NodeType::FileImport {
                     file_path: None,
                     file_name: None,
+                    watch: false,
+                    modified: None,
+                    format: None,
+                    flatten: false,
+                },
+            ),
+            NodeType::FolderImport { .. } => (
+                "Folder Import".to_string(),
+                260.0,
+                240.0,
+                NodeType::FolderImport {
+                    folder_path: None,
+                    include: default_include(),
+                    exclude: String::new(),
+                    output: FolderOutput::default(),
+                    max_size_kb: default_max_size_kb(),
+                    files: Vec::new(),
+                    left_out: Vec::new(),
                 },
             ),
             NodeType::FileExport { .. } => (
                 "File Export".to_string(),
-                200.0,
-                150.0,
+                220.0,
+                220.0,
                 NodeType::FileExport {
                     folder_path: None,
                     file_name: None,
                     file_type: "txt".to_string(),
+                    write_mode: WriteMode::default(),
+                    saved_path: None,
                 },
             ),
             NodeType::Model { provider, .. } => {
-                let (title, model_name) = match provider {
-                    ProviderType::Ollama => ("Ollama", ""),
-                    ProviderType::Anthropic => ("Anthropic", "claude-sonnet-4-20250514"),
+                let model_name = match provider {
+                    ProviderType::Ollama | ProviderType::OpenAICompatible { .. } => "",
+                    ProviderType::Anthropic => "claude-sonnet-4-20250514",
+                    ProviderType::OpenAI => "gpt-4.1",
+                    ProviderType::Google => "gemini-2.5-flash",
+                };
+                let title = match provider {
+                    ProviderType::OpenAICompatible { endpoint } => endpoint.as_str(),
+                    _ => provider.display_name(),
                 };
                 
                 (
@@ -113,7 +436,10 @@ impl Node {
                         provider: provider.clone(),
                         model_name: model_name.to_string(),
                         messages: Vec::new(),
-                        thinking: false
+                        thinking: false,
+                        options: ModelOptions::default(),
+                        system_prompt: String::new(),
+                        bypass_cache: false,
                     },
                 )
             },
@@ -128,22 +454,40 @@ impl Node {
             height,
             title,
             input: None,
+            system_input: None,
             output: None,
             drag_offset_x: 0.0,
             drag_offset_y: 0.0,
             is_maximized: false,
             needs_execution: true,
-            is_executing: false
+            is_executing: false,
+            error: None,
+            skipped: false,
         }
     }
     
     pub fn prepare_prompt(&self) -> anyhow::Result<Vec<ChatMessage>> {
-        let chat_messages = match &self.node_type {
-            NodeType::Model { messages, .. } => messages,
+        let (chat_messages, system_prompt) = match &self.node_type {
+            NodeType::Model { messages, system_prompt, .. } => (messages, system_prompt),
             _ => return Err(anyhow::anyhow!("prepare_prompt called on non-model node")),
         };
     
         let mut messages = Vec::new();
+        // The typed system prompt comes first, followed by anything wired into the system port
+        let system = [Some(system_prompt), self.system_input.as_ref()]
+            .into_iter()
+            .flatten()
+            .filter(|part| !part.trim().is_empty())
+            .cloned()
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        if !system.is_empty() {
+            messages.push(ChatMessage {
+                role: MessageRole::System,
+                content: system,
+                thinking: None
+            });
+        }
         if let Some(input) = &self.input {
             if !input.trim().is_empty() {
                 messages.push(ChatMessage {
@@ -154,30 +498,72 @@ impl Node {
             }
         }
         messages.extend(chat_messages.clone());
-        if messages.is_empty() {
+        if messages.iter().all(|message| message.role == MessageRole::System) {
             return Err(anyhow::anyhow!("No input or messages provided to model"));
         }
         Ok(messages)
     }
+    pub fn set_error(&mut self, error: &anyhow::Error) {
+        self.error = Some(NodeError::new(error));
+    }
+    
     pub fn reset(&mut self) {
         self.output = None;
+        self.error = None;
+        self.skipped = false;
         self.needs_execution = true;
         match &mut self.node_type {
             NodeType::Model { messages, .. } => {
                 messages.clear();
             },
-            NodeType::FileImport { file_path, file_name } => {
+            NodeType::Template { template } => {
+                template.clear();
+            },
+            NodeType::Loop { iterations, .. } => {
+                iterations.clear();
+            },
+            NodeType::Map { results, .. } => {
+                results.clear();
+            },
+            NodeType::Review { status, edited } => {
+                *status = ReviewStatus::Open;
+                *edited = None;
+            },
+            NodeType::FileImport { file_path, file_name, watch, modified, format, flatten } => {
                 *file_path = None;
                 *file_name = None;
+                *watch = false;
+                *modified = None;
+                *format = None;
+                *flatten = false;
+            },
+            NodeType::FolderImport { folder_path, include, exclude, output, max_size_kb, files, left_out } => {
+                *folder_path = None;
+                *include = default_include();
+                exclude.clear();
+                *output = FolderOutput::default();
+                *max_size_kb = default_max_size_kb();
+                files.clear();
+                left_out.clear();
             },
-            NodeType::FileExport { folder_path, file_name, file_type } => {
+            NodeType::FileExport { folder_path, file_name, file_type, write_mode, saved_path } => {
                 *folder_path = None;
                 *file_name = None;
                 *file_type = "txt".to_string();
+                *write_mode = WriteMode::default();
+                *saved_path = None;
             },
             _ => {}
         }
     }
+    
+    /// What the node passes on through one of its outputs
+    pub fn port_output(&self, port: &str) -> Option<&String> {
+        match &self.node_type {
+            NodeType::Map { item, .. } if port == ITEM_PORT => item.as_ref(),
+            _ => self.output.as_ref(),
+        }
+    }
 }
 
 #[component]
@@ -220,27 +606,54 @@ pub fn NodeComponent(
             }
         }
     };
-    let on_start_connection = move |(id, event_data): (usize, Event<MouseData>)| {
+    let on_start_connection = move |(id, port, event_data): (usize, String, Event<MouseData>)| {
         event_data.prevent_default();
         let mut ws_writer = workflow_state.write();
         let cs_reader = canvas_state.read();
     
         if let Some(node) = ws_writer.nodes.get(&id) {
-            let (port_world_x, port_world_y) = get_port_world_pos(node, "output");
+            let (port_world_x, port_world_y) = get_port_world_pos(node, &port);
             let (port_center_page_x, port_center_page_y) = cs_reader.world_to_page_coords(port_world_x, port_world_y);
-            ws_writer.start_drawing_connection(id, port_center_page_x, port_center_page_y, &cs_reader);
+            ws_writer.start_drawing_connection(id, &port, port_center_page_x, port_center_page_y, &cs_reader);
         }
     };
-    let on_connection_redirect = move |(id, event_data): (usize, Event<MouseData>)| {
+    let on_connection_redirect = move |(id, port, event_data): (usize, String, Event<MouseData>)| {
         event_data.prevent_default();
-        workflow_state.write().redirect_connection(id);
+        workflow_state.write().redirect_connection(id, &port);
     };
     let on_delete = move |_| {
         workflow_state.write().remove_node(node_id);
     };
+    let mut on_stop = move |id: usize| {
+        workflow_state.write().stop_node(id);
+    };
     let on_reset = move |_| {
-        if let Some(node) = workflow_state.write().nodes.get_mut(&node_id) {
+        let mut ws = workflow_state.write();
+        if let Some(node) = ws.nodes.get_mut(&node_id) {
             node.reset();
+            ws.mark_dirty(node_id);
+        }
+    };
+    let on_run = move |scope: RunScope| {
+        start_workflow_run(workflow_state, scope);
+    };
+    let on_retry = move |_| {
+        let node_type = workflow_state.read().nodes.get(&node_id).map(|n| n.node_type.clone());
+        match node_type {
+            Some(NodeType::Model { .. }) => retry_model_node(workflow_state, node_id),
+            Some(NodeType::FileImport { .. }) => {
+                spawn(async move {
+                    load_import_file(workflow_state, node_id).await;
+                });
+            }
+            Some(NodeType::FolderImport { .. }) => {
+                spawn(load_folder_import(workflow_state, node_id));
+            }
+            _ => {
+                if let Some(node) = workflow_state.write().nodes.get_mut(&node_id) {
+                    node.error = None;
+                }
+            }
         }
     };
     let mut on_toggle_maximize = move |id: usize| {
@@ -261,8 +674,9 @@ pub fn NodeComponent(
         
         // Check each target - if any has multiple inputs, return our order number
         for target_id in target_node_ids {
-            if let Some(order) = ws.get_input_order_number(node_id, target_id) {
-                return Some(order);
+            if let Some((conn_id, order)) = ws.get_input_order_number(node_id, target_id) {
+                let target_title = ws.nodes.get(&target_id).map(|n| n.title.clone()).unwrap_or_default();
+                return Some((conn_id, order, target_title));
             }
         }
         None
@@ -295,9 +709,10 @@ pub fn NodeComponent(
     let node_style = format!(
         "position: absolute; left: {}px; top: {}px; width: {}px; height: {}px; \
         background-color: var(--ui); border-radius: 8px; \
-        display: flex; flex-direction: column; overflow: hidden; z-index: {};",
+        display: flex; flex-direction: column; overflow: hidden; z-index: {}; opacity: {};",
         node_position_x, node_position_y, node_width, node_height,
-        if node.is_maximized { 1000 } else { 1 }
+        if node.is_maximized { 1000 } else { 1 },
+        if node.skipped { 0.5 } else { 1.0 }
     );
 
     let body = match &node.node_type {
@@ -312,6 +727,61 @@ pub fn NodeComponent(
                 }
             }
         }
+        NodeType::Template { .. } => {
+            rsx! {
+                TemplateNode {
+                    node: node.clone(),
+                    workflow_state,
+                    canvas_zoom,
+                    on_start_connection,
+                    on_connection_redirect,
+                }
+            }
+        }
+        NodeType::Router {} => {
+            rsx! {
+                RouterNode {
+                    node: node.clone(),
+                    workflow_state,
+                    canvas_zoom,
+                    on_start_connection,
+                    on_connection_redirect,
+                }
+            }
+        }
+        NodeType::Loop { .. } => {
+            rsx! {
+                LoopNode {
+                    node: node.clone(),
+                    workflow_state,
+                    canvas_zoom,
+                    on_start_connection,
+                    on_connection_redirect,
+                }
+            }
+        }
+        NodeType::Map { .. } => {
+            rsx! {
+                MapNode {
+                    node: node.clone(),
+                    workflow_state,
+                    canvas_zoom,
+                    on_start_connection,
+                    on_connection_redirect,
+                }
+            }
+        }
+        NodeType::Review { .. } => {
+            rsx! {
+                ReviewNode {
+                    node: node.clone(),
+                    workflow_state,
+                    canvas_zoom,
+                    on_start_connection,
+                    on_connection_redirect,
+                }
+            }
+        }
         NodeType::FileImport { .. } => {
             rsx! {
                 FileImportNode {
@@ -323,6 +793,17 @@ pub fn NodeComponent(
                 }
             }
         }
+        NodeType::FolderImport { .. } => {
+            rsx! {
+                FolderImportNode {
+                    node: node.clone(),
+                    workflow_state,
+                    canvas_zoom,
+                    on_start_connection,
+                    on_connection_redirect,
+                }
+            }
+        }
         NodeType::FileExport { .. } => {
             rsx! {
                 FileExportNode {
@@ -353,6 +834,7 @@ pub fn NodeComponent(
         div {
             class: "node",
             style: "{node_style}",
+            title: if node.skipped { "Skipped, no route led here" },
             onmousedown: on_mouse_down,
             onmouseenter: move |_| {
                 let mut ws = workflow_state.write();
@@ -374,11 +856,17 @@ pub fn NodeComponent(
                 "{node.title}"
                 
                 // Order number badge
-                if let Some(order) = input_order_number() {
+                if let Some((conn_id, order, target_title)) = input_order_number() {
                     div {
                         style: "position: absolute; top: 0px; left: 0px; color: var(--text-link); 
                                width: 24px; height: 100%; border-radius: 8px; display: flex; align-items: center; 
-                               justify-content: center; font-size: 16px; font-weight: bold;",
+                               justify-content: center; font-size: 16px; font-weight: bold; cursor: pointer;",
+                        title: "Input {order} of {target_title}, click to move it earlier, Shift+click to move it later",
+                        onmousedown: move |event| {
+                            event.stop_propagation();
+                            let earlier = !event.modifiers().shift();
+                            workflow_state.write().move_connection(conn_id, earlier);
+                        },
                         "{order}"
                     }
                 }
@@ -386,6 +874,20 @@ pub fn NodeComponent(
                 div {
                     style: "position: absolute; top: 50%; right: 8px; transform: translateY(-50%); 
                             display: flex; gap: 4px;",
+                    
+                    // Stop button
+                    if node.is_executing {
+                        div {
+                            style: "width: 20px; height: 20px; cursor: pointer; display: flex; align-items: center; 
+                                    justify-content: center; font-size: 12px; color: var(--text-error);",
+                            title: "Stop",
+                            onmousedown: move |event| {
+                                event.stop_propagation();
+                                on_stop(node.id);
+                            },
+                            "■" 
+                        }
+                    }
                    
                     // Maximize button
                     div {
@@ -399,6 +901,28 @@ pub fn NodeComponent(
                     }
                 }
             }
+            if let Some(error) = &node.error {
+                div {
+                    class: "node-error",
+                    style: "display: flex; align-items: flex-start; gap: 6px; padding: 6px 10px; 
+                            font-size: 12px; color: var(--text-error); background-color: var(--bg-alt);",
+                    div {
+                        style: "flex: 1; max-height: 60px; overflow-y: auto; word-break: break-word;",
+                        onwheel: move |event| event.stop_propagation(),
+                        title: "{error.message}",
+                        span { style: "font-weight: bold;", "{error.kind.label()}: " }
+                        "{error.message}"
+                    }
+                    button {
+                        style: "background: transparent; color: var(--text-primary); border: none; 
+                                cursor: pointer; font-size: 12px; font-weight: bold; padding: 0 4px;",
+                        disabled: node.is_executing,
+                        onmousedown: move |event| event.stop_propagation(),
+                        onclick: on_retry,
+                        "Retry"
+                    }
+                }
+            }
             div { class: "node-content", style: "flex: 1; padding: 10px; display: flex; flex-direction: column; overflow-y: auto;",
                 {body}
             }
@@ -407,8 +931,12 @@ pub fn NodeComponent(
                     visible: node_context_menu_visible,
                     position_x: node_context_menu_pos_x,
                     position_y: node_context_menu_pos_y,
+                    node_id,
+                    is_model: matches!(node.node_type, NodeType::Model { .. }),
+                    is_running: workflow_state.read().is_running(),
                     on_delete,
-                    on_reset
+                    on_reset,
+                    on_run
                 }
             }
         }
@@ -420,8 +948,8 @@ fn PromptNode(
     node: Node,
     workflow_state: Signal<Workflow>,
     canvas_zoom: f64,
-    on_start_connection: EventHandler<(usize, Event<MouseData>)>,
-    on_connection_redirect: EventHandler<(usize, Event<MouseData>)>,
+    on_start_connection: EventHandler<(usize, String, Event<MouseData>)>,
+    on_connection_redirect: EventHandler<(usize, String, Event<MouseData>)>,
 ) -> Element {
     let mut on_context_content_change = move |(target_node_id, new_content): (usize, String)| {
         workflow_state.write().update_node_output(target_node_id, new_content);
@@ -453,10 +981,20 @@ fn PromptNode(
 pub fn NodeSocket(
     node_id: usize,
     canvas_zoom: f64,
-    on_start_connection: EventHandler<(usize, Event<MouseData>)>,
-    on_connection_redirect: EventHandler<(usize, Event<MouseData>)>,
+    on_start_connection: EventHandler<(usize, String, Event<MouseData>)>,
+    on_connection_redirect: EventHandler<(usize, String, Event<MouseData>)>,
     input: bool,
-    output: bool
+    output: bool,
+    #[props(default)]
+    system: bool,
+    /// The feedback and judge ports of a Loop node
+    #[props(default)]
+    loop_ports: bool,
+    /// The collect input and the item output of a Map node
+    #[props(default)]
+    map_ports: bool,
+    #[props(default)]
+    on_port_hover: EventHandler<Option<String>>,
 ) -> Element {
     let socket_size = (12.0 / canvas_zoom).clamp(12.0, 25.0);
     
@@ -473,7 +1011,64 @@ pub fn NodeSocket(
                 style: "{socket_style} left: 0; transform: translate(-50%, -50%);",
                 onmousedown: move |event| {
                     event.stop_propagation();
-                    on_connection_redirect.call((node_id, event.clone()));
+                    on_connection_redirect.call((node_id, INPUT_PORT.to_string(), event.clone()));
+                }
+            }
+        }
+        
+        if system {
+            div {
+                class: "node-input-socket",
+                title: "System prompt",
+                style: "{socket_style} left: 0; top: 75%; background-color: var(--syntax-keyword); transform: translate(-50%, -50%);",
+                onmousedown: move |event| {
+                    event.stop_propagation();
+                    on_connection_redirect.call((node_id, SYSTEM_PORT.to_string(), event.clone()));
+                },
+                onmouseenter: move |_| on_port_hover.call(Some(SYSTEM_PORT.to_string())),
+                onmouseleave: move |_| on_port_hover.call(None)
+            }
+        }
+        
+        if loop_ports {
+            for (port, top, color, label) in [
+                (FEEDBACK_PORT, "70%", "var(--syntax-number)", "Value fed back into the loop"),
+                (JUDGE_PORT, "85%", "var(--syntax-constant)", "Judge, its answer can end the loop"),
+            ] {
+                div {
+                    key: "{port}",
+                    class: "node-input-socket",
+                    title: label,
+                    style: "{socket_style} left: 0; top: {top}; background-color: {color}; transform: translate(-50%, -50%);",
+                    onmousedown: move |event| {
+                        event.stop_propagation();
+                        on_connection_redirect.call((node_id, port.to_string(), event.clone()));
+                    },
+                    onmouseenter: move |_| on_port_hover.call(Some(port.to_string())),
+                    onmouseleave: move |_| on_port_hover.call(None)
+                }
+            }
+        }
+        
+        if map_ports {
+            div {
+                class: "node-input-socket",
+                title: "Results of the items",
+                style: "{socket_style} left: 0; top: 75%; background-color: var(--syntax-number); transform: translate(-50%, -50%);",
+                onmousedown: move |event| {
+                    event.stop_propagation();
+                    on_connection_redirect.call((node_id, COLLECT_PORT.to_string(), event.clone()));
+                },
+                onmouseenter: move |_| on_port_hover.call(Some(COLLECT_PORT.to_string())),
+                onmouseleave: move |_| on_port_hover.call(None)
+            }
+            div {
+                class: "node-output-socket",
+                title: "The current item",
+                style: "{socket_style} right: 0; top: 30%; background-color: var(--syntax-number); transform: translate(50%, -50%);",
+                onmousedown: move |event| {
+                    event.stop_propagation();
+                    on_start_connection.call((node_id, ITEM_PORT.to_string(), event.clone()));
                 }
             }
         }
@@ -484,7 +1079,7 @@ pub fn NodeSocket(
                 style: "{socket_style} right: 0; transform: translate(50%, -50%);",
                 onmousedown: move |event| {
                     event.stop_propagation();
-                    on_start_connection.call((node_id, event.clone()));
+                    on_start_connection.call((node_id, OUTPUT_PORT.to_string(), event.clone()));
                 }
             }
         }
@@ -496,8 +1091,12 @@ pub fn NodeContextMenu(
     visible: Signal<bool>,
     position_x: Signal<f64>,
     position_y: Signal<f64>,
+    node_id: usize,
+    is_model: bool,
+    is_running: bool,
     on_delete: EventHandler<()>,
     on_reset: EventHandler<()>,
+    on_run: EventHandler<RunScope>,
 ) -> Element {
     let menu_item_style = "padding: 8px 15px; cursor: pointer; user-select: none; \
         display: flex; align-items: center; justify-content: space-between;";
@@ -540,6 +1139,48 @@ pub fn NodeContextMenu(
                     "Reset"
                 }
             }
+            
+            // Only one run at a time, the toolbar stops the current one
+            if !is_running {
+                div {
+                    style: "margin: 5px 0; border-top: 1px solid #ccc;",
+                }
+                if is_model {
+                    div {
+                        style: "{menu_item_style}",
+                        onclick: move |_| {
+                            on_run.call(RunScope::Node(node_id));
+                            visible.set(false);
+                        },
+                        span {
+                            style: "color: var(--ui);",
+                            "Run this node"
+                        }
+                    }
+                }
+                div {
+                    style: "{menu_item_style}",
+                    onclick: move |_| {
+                        on_run.call(RunScope::Downstream(node_id));
+                        visible.set(false);
+                    },
+                    span {
+                        style: "color: var(--ui);",
+                        "Run from here"
+                    }
+                }
+                div {
+                    style: "{menu_item_style}",
+                    onclick: move |_| {
+                        on_run.call(RunScope::Upstream(node_id));
+                        visible.set(false);
+                    },
+                    span {
+                        style: "color: var(--ui);",
+                        "Run up to here"
+                    }
+                }
+            }
         }
     }
 }
\ No newline at end of file
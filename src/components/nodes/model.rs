@@ -1,10 +1,12 @@
 use dioxus::prelude::*;
 use crate::{
+    executor::WorkflowExecutor,
     components::{
-        workflow::Workflow,
+        workflow::{Workflow, apply_events},
         nodes::{Node, NodeType, NodeSocket, ChatMessage, MessageRole, ProviderType}
     }, 
-    llm::{AnthropicClient, ApiManager, LLMProvider, OllamaClient}
+    llm::{AnthropicClient, ApiManager, GeminiClient, LLMProvider, OllamaClient, OpenAIClient},
+    file_manager
 };
 
 #[component]
@@ -12,8 +14,8 @@ pub fn ModelNode(
     node: Node,
     workflow_state: Signal<Workflow>,
     canvas_zoom: f64,
-    on_start_connection: EventHandler<(usize, Event<MouseData>)>,
-    on_connection_redirect: EventHandler<(usize, Event<MouseData>)>,
+    on_start_connection: EventHandler<(usize, String, Event<MouseData>)>,
+    on_connection_redirect: EventHandler<(usize, String, Event<MouseData>)>,
     provider: ProviderType,
     model_name: String,
     messages: Vec<ChatMessage>
@@ -21,7 +23,12 @@ pub fn ModelNode(
     let mut current_message = use_signal(|| "".to_string());
     let mut available_models = use_signal(Vec::<String>::new);
     let mut show_thoughts = use_signal(|| false);
+    let mut show_options = use_signal(|| false);
     let mut api_key_available = use_signal(|| false);
+    let setup_warning = match &provider {
+        ProviderType::OpenAICompatible { endpoint } => format!("⚠️ Endpoint \"{}\" is not configured in settings", endpoint),
+        _ => format!("⚠️ Configure your {} API key in settings to use this provider", provider.display_name()),
+    };
     
     // Fetch models when component mounts or provider changes
     use_effect(move || {
@@ -46,6 +53,31 @@ pub fn ModelNode(
                     }
                     let client = AnthropicClient::new();
                     client.get_available_models().await.unwrap_or_else(|_| vec!["claude-sonnet-4-20250514".to_string()])
+                },
+                ProviderType::OpenAI => {
+                    if api_manager.get_openai_key().is_err() {
+                        api_key_available.set(false);
+                        return;
+                    }
+                    let client = OpenAIClient::new();
+                    client.get_available_models().await.unwrap_or_else(|_| vec!["gpt-4.1".to_string()])
+                },
+                ProviderType::Google => {
+                    if api_manager.get_google_key().is_err() {
+                        api_key_available.set(false);
+                        return;
+                    }
+                    let client = GeminiClient::new();
+                    client.get_available_models().await.unwrap_or_else(|_| vec!["gemini-2.5-flash".to_string()])
+                },
+                ProviderType::OpenAICompatible { endpoint } => {
+                    let config = file_manager::load_provider_config().unwrap_or_default();
+                    let Some(endpoint_config) = config.endpoint(&endpoint) else {
+                        api_key_available.set(false);
+                        return;
+                    };
+                    let client = OpenAIClient::for_endpoint(endpoint_config);
+                    client.get_available_models().await.unwrap_or_else(|_| vec!["".to_string()])
                 }
             };
             api_key_available.set(true);
@@ -55,48 +87,64 @@ pub fn ModelNode(
     
     let mut on_model_change = move |(target_node_id, new_model): (usize, String)| {
         let mut ws = workflow_state.write();
-        if let Some(node_to_update) = ws.nodes.get_mut(&target_node_id) {
-            if let NodeType::Model { model_name, .. } = &mut node_to_update.node_type {
+        if let Some(NodeType::Model { model_name, .. }) = ws.nodes.get_mut(&target_node_id).map(|n| &mut n.node_type) {
+            if *model_name != new_model {
                 *model_name = new_model;
+                ws.mark_dirty(target_node_id);
             }
         }
     };
     
+    let on_port_hover = move |port: Option<String>| {
+        let mut ws = workflow_state.write();
+        if ws.drawing_connection_state.active {
+            ws.set_connection_target_port(port);
+        }
+    };
+    
+    let mut on_system_prompt_change = move |(target_node_id, new_prompt): (usize, String)| {
+        let mut ws = workflow_state.write();
+        if let Some(NodeType::Model { system_prompt, .. }) = ws.nodes.get_mut(&target_node_id).map(|n| &mut n.node_type) {
+            *system_prompt = new_prompt;
+            ws.mark_dirty(target_node_id);
+        }
+    };
+    
+    let mut on_option_change = move |(target_node_id, field, value): (usize, &'static str, String)| {
+        let mut ws = workflow_state.write();
+        let Some(node_to_update) = ws.nodes.get_mut(&target_node_id) else { return };
+        let NodeType::Model { options, .. } = &mut node_to_update.node_type else { return };
+        
+        // Empty or unparsable input clears the option so the provider default applies
+        let value = value.trim();
+        match field {
+            "temperature" => options.temperature = value.parse().ok(),
+            "top_p" => options.top_p = value.parse().ok(),
+            "top_k" => options.top_k = value.parse().ok(),
+            "max_tokens" => options.max_tokens = value.parse().ok(),
+            "stop" => options.stop = parse_stop_sequences(value),
+            "seed" => options.seed = value.parse().ok(),
+            "num_ctx" => options.num_ctx = value.parse().ok(),
+            _ => return,
+        }
+        ws.mark_dirty(target_node_id);
+    };
+    
     let mut on_send_message = move |(target_node_id, message_content): (usize, String)| {
-        let (provider_type, model_name_clone, thinking_enabled, ollama_messages) = {
+        {
             let mut ws = workflow_state.write();
             let Some(node_to_update) = ws.nodes.get_mut(&target_node_id) else { return };
-            let NodeType::Model { provider, model_name, messages, thinking } = &mut node_to_update.node_type else { return };
+            if node_to_update.is_executing { return }
+            let NodeType::Model { messages, .. } = &mut node_to_update.node_type else { return };
             
             messages.push(ChatMessage {
                 role: MessageRole::User,
                 content: message_content.clone(),
                 thinking: None
             });
-            
-            let provider_type = provider.clone();
-            let model_name_clone = model_name.clone();
-            let thinking_enabled = *thinking;
-            node_to_update.is_executing = true;
-            
-            match node_to_update.prepare_prompt() {
-                Ok(ollama_messages) => (provider_type, model_name_clone, thinking_enabled, ollama_messages),
-                Err(e) => {
-                    println!("Failed to prepare prompt: {}", e);
-                    node_to_update.is_executing = false;
-                    return;
-                }
-            }
-        };
+        }
         
-        spawn(execute_model_node(
-            workflow_state,
-            target_node_id,
-            ollama_messages,
-            provider_type,
-            Some(model_name_clone),
-            Some(thinking_enabled)
-        ));
+        retry_model_node(workflow_state, target_node_id);
     };
     
     if !api_key_available() {
@@ -104,7 +152,7 @@ pub fn ModelNode(
             div {
                 style: "color: var(--text-warning); padding: 10px; text-align: center; 
                     background-color: var(--bg-alt); border-radius: 4px;",
-                "⚠️ Configure your Anthropic API key in settings to use this provider"
+                "{setup_warning}"
             }
         };
     }
@@ -117,7 +165,9 @@ pub fn ModelNode(
                 on_start_connection,
                 on_connection_redirect,
                 input: true,
-                output: true
+                output: true,
+                system: true,
+                on_port_hover: on_port_hover
             }
             
             div { 
@@ -128,6 +178,7 @@ pub fn ModelNode(
                         style: match message.role {
                             MessageRole::User => "display: flex; flex-direction: column; align-items: flex-end; margin-bottom: 8px;",
                             MessageRole::Assistant => "display: flex; flex-direction: column; align-items: flex-start; margin-bottom: 8px;",
+                            MessageRole::System => "display: flex; flex-direction: column; align-items: center; margin-bottom: 8px;",
                         },
                         div {
                             style: "background-color: var(--bg-alt); padding: 8px 12px; border-radius: 12px; max-width: 70%;",
@@ -219,6 +270,29 @@ pub fn ModelNode(
             node.output.as_deref().unwrap_or("Response")
         };
         
+        let system_prompt = match &node.node_type {
+            NodeType::Model { system_prompt, .. } => system_prompt.clone(),
+            _ => String::new(),
+        };
+        let option_fields: Vec<(&'static str, &'static str, String)> = match &node.node_type {
+            NodeType::Model { provider, options, .. } => supported_options(provider)
+                .iter()
+                .map(|&field| {
+                    let (label, value) = match field {
+                        "temperature" => ("Temperature", options.temperature.map(|v| v.to_string())),
+                        "top_p" => ("Top P", options.top_p.map(|v| v.to_string())),
+                        "top_k" => ("Top K", options.top_k.map(|v| v.to_string())),
+                        "max_tokens" => ("Max tokens", options.max_tokens.map(|v| v.to_string())),
+                        "stop" => ("Stop", Some(format_stop_sequences(&options.stop))),
+                        "seed" => ("Seed", options.seed.map(|v| v.to_string())),
+                        _ => ("Context", options.num_ctx.map(|v| v.to_string())),
+                    };
+                    (field, label, value.unwrap_or_default())
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        
         rsx! {
             NodeSocket{
                 node_id: node.id,
@@ -226,7 +300,9 @@ pub fn ModelNode(
                 on_start_connection,
                 on_connection_redirect,
                 input: true,
-                output: true
+                output: true,
+                system: true,
+                on_port_hover: on_port_hover
             }
             
             div {
@@ -264,10 +340,9 @@ pub fn ModelNode(
                             checked: if let NodeType::Model { thinking, .. } = &node.node_type { *thinking } else { false },
                             onchange: move |event| {
                                 let mut ws = workflow_state.write();
-                                if let Some(node_to_update) = ws.nodes.get_mut(&node.id) {
-                                    if let NodeType::Model { thinking, .. } = &mut node_to_update.node_type {
-                                        *thinking = event.checked();
-                                    }
+                                if let Some(NodeType::Model { thinking, .. }) = ws.nodes.get_mut(&node.id).map(|n| &mut n.node_type) {
+                                    *thinking = event.checked();
+                                    ws.mark_dirty(node.id);
                                 }
                             },
                             onmousedown: |evt| evt.stop_propagation(),
@@ -278,6 +353,29 @@ pub fn ModelNode(
                             style: "font-size: 12px; cursor: pointer; user-select: none; color: var(--text-primary)",
                             "Thinking"
                         }
+                        input {
+                            r#type: "checkbox",
+                            id: "bypass-cache-{node.id}",
+                            checked: if let NodeType::Model { bypass_cache, .. } = &node.node_type { *bypass_cache } else { false },
+                            onchange: move |event| {
+                                let mut ws = workflow_state.write();
+                                if let Some(NodeType::Model { bypass_cache, .. }) = ws.nodes.get_mut(&node.id).map(|n| &mut n.node_type) {
+                                    *bypass_cache = event.checked();
+                                    // Turning the cache off asks for a fresh answer on the next run
+                                    if *bypass_cache {
+                                        ws.mark_dirty(node.id);
+                                    }
+                                }
+                            },
+                            onmousedown: |evt| evt.stop_propagation(),
+                            style: "cursor: pointer; margin-left: 8px;",
+                        },
+                        label {
+                            r#for: "bypass-cache-{node.id}",
+                            title: "Always ask the provider instead of reusing the answer to the same prompt",
+                            style: "font-size: 12px; cursor: pointer; user-select: none; color: var(--text-primary)",
+                            "No cache"
+                        }
                         if let NodeType::Model { thinking, .. } = &node.node_type {
                             if *thinking {
                                 button {
@@ -292,6 +390,48 @@ pub fn ModelNode(
                             }
                         }
                     }
+                    
+                    if !option_fields.is_empty() {
+                        div {
+                            style: "display: flex; flex-direction: column; gap: 6px;",
+                            div {
+                                style: "font-size: 12px; cursor: pointer; user-select: none; color: var(--text-primary);",
+                                onclick: move |_| show_options.set(!show_options()),
+                                onmousedown: |evt| evt.stop_propagation(),
+                                if show_options() { "▾ Options" } else { "▸ Options" }
+                            }
+                            if show_options() {
+                                textarea {
+                                    style: "min-height: 48px; resize: vertical; background: var(--bg-alt); border: none; border-radius: 4px; 
+                                        color: var(--text-primary); padding: 4px 6px; font-size: 11px;",
+                                    placeholder: "System prompt...",
+                                    value: "{system_prompt}",
+                                    oninput: move |event| on_system_prompt_change((node.id, event.value())),
+                                    onmousedown: |evt| evt.stop_propagation(),
+                                    onwheel: |evt| evt.stop_propagation(),
+                                }
+                                div {
+                                    style: "display: grid; grid-template-columns: auto 1fr; gap: 4px 8px; align-items: center;",
+                                    for (field, label, value) in option_fields {
+                                        label {
+                                            style: "font-size: 11px; color: var(--text-muted);",
+                                            "{label}"
+                                        }
+                                        input {
+                                            r#type: if field == "stop" { "text" } else { "number" },
+                                            step: if field == "temperature" || field == "top_p" { "0.05" } else { "1" },
+                                            placeholder: if field == "stop" { "comma separated, \\n for newline" } else { "default" },
+                                            value: "{value}",
+                                            onchange: move |event| on_option_change((node.id, field, event.value())),
+                                            onmousedown: |evt| evt.stop_propagation(),
+                                            style: "background: var(--bg-alt); border: none; border-radius: 4px; 
+                                                color: var(--text-primary); padding: 4px 6px; font-size: 11px; min-width: 0;",
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
                 }
                 
                 div { 
@@ -308,68 +448,45 @@ pub fn ModelNode(
 }
 
 
-pub async fn execute_model_node(
-    mut workflow_state: Signal<Workflow>,
-    node_id: usize,
-    messages: Vec<ChatMessage>,
-    provider_type: ProviderType,
-    model_name: Option<String>,
-    thinking_enabled: Option<bool>
-) {
-    let result = match provider_type {
-        ProviderType::Ollama => {
-            let client = OllamaClient::new();
-            client.generate(model_name, messages, thinking_enabled).await
-        },
-        ProviderType::Anthropic => {
-            let client = AnthropicClient::new();
-            client.generate(model_name, messages, thinking_enabled).await
-        }
-    };
-    
-    let mut receiver = match result {
-        Ok(recv) => recv,
-        Err(e) => {
-            println!("Failed to execute node {}: {}", node_id, e);
-            if let Some(n) = workflow_state.write().nodes.get_mut(&node_id) {
-                n.is_executing = false;
-            }
-            return;
-        }
-    };
-    
-    let mut ws_write = workflow_state.write();
-    if let Some(NodeType::Model { messages, .. }) = ws_write.nodes.get_mut(&node_id).map(|n| &mut n.node_type) {
-        messages.push(ChatMessage {
-            role: MessageRole::Assistant,
-            content: String::new(),
-            thinking: None,
-        });
+/// Sampling parameters each provider's API accepts, in display order
+fn supported_options(provider: &ProviderType) -> &'static [&'static str] {
+    match provider {
+        ProviderType::Ollama => &["temperature", "top_p", "top_k", "max_tokens", "stop", "seed", "num_ctx"],
+        ProviderType::Anthropic => &["temperature", "top_p", "top_k", "max_tokens", "stop"],
+        ProviderType::OpenAI => &["temperature", "top_p", "max_tokens", "stop", "seed"],
+        ProviderType::Google | ProviderType::OpenAICompatible { .. } => &["temperature", "top_p", "top_k", "max_tokens", "stop", "seed"],
     }
-    drop(ws_write);
-    
-    let mut full_response = String::new();
-    while let Some(message_chunk) = receiver.recv().await {
-        let mut ws_write = workflow_state.write();
-        if let Some(NodeType::Model { messages, .. }) = ws_write.nodes.get_mut(&node_id).map(|n| &mut n.node_type) {
-            if let Some(last_msg) = messages.last_mut() {
-                if let Some(thinking_chunk) = &message_chunk.thinking {
-                    match &mut last_msg.thinking {
-                        Some(existing) => existing.push_str(thinking_chunk),
-                        None => last_msg.thinking = Some(thinking_chunk.clone()),
-                    }
-                }
-                
-                if !message_chunk.content.is_empty() {
-                    last_msg.content.push_str(&message_chunk.content);
-                    full_response.push_str(&message_chunk.content);
-                }
-            }
-        }
-        ws_write.update_node_output(node_id, full_response.clone());
+}
+
+fn parse_stop_sequences(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|stop| stop.trim_matches(' ').replace("\\n", "\n"))
+        .filter(|stop| !stop.is_empty())
+        .collect()
+}
+
+fn format_stop_sequences(stop: &[String]) -> String {
+    stop.iter()
+        .map(|stop| stop.replace('\n', "\\n"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Run the node's conversation (again), used for sending a chat message and for retrying after an error
+pub fn retry_model_node(mut workflow_state: Signal<Workflow>, node_id: usize) {
+    if workflow_state.read().nodes.get(&node_id).is_none_or(|n| n.is_executing) {
+        return;
     }
     
-    if let Some(n) = workflow_state.write().nodes.get_mut(&node_id) {
-        n.is_executing = false;
+    let (events_tx, events_rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut executor = WorkflowExecutor::new(workflow_state.read().clone(), events_tx);
+    // Marked right away so a second send can't start before the executor reports the start
+    if let Some(node) = workflow_state.write().nodes.get_mut(&node_id) {
+        node.is_executing = true;
     }
+    spawn(async move {
+        executor.run_nodes(&[node_id]).await;
+    });
+    spawn(apply_events(workflow_state, events_rx));
 }
\ No newline at end of file
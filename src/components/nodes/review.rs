@@ -0,0 +1,99 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::components::{
+    nodes::{Node, NodeType, NodeSocket},
+    Workflow
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewStatus {
+    /// The current input hasn't been looked at yet
+    #[default]
+    Open,
+    Approved,
+    /// The nodes after the review are passed over
+    Rejected,
+}
+
+/// The answer to a Review node, approving passes the possibly edited text on
+#[derive(Clone, Debug, PartialEq)]
+pub enum ReviewDecision {
+    Approve(String),
+    Reject,
+}
+
+#[component]
+pub fn ReviewNode(
+    node: Node,
+    workflow_state: Signal<Workflow>,
+    canvas_zoom: f64,
+    on_start_connection: EventHandler<(usize, String, Event<MouseData>)>,
+    on_connection_redirect: EventHandler<(usize, String, Event<MouseData>)>,
+) -> Element {
+    let NodeType::Review { status, edited } = node.node_type.clone() else {
+        return rsx! {};
+    };
+    let waiting = workflow_state.read().review_replies.contains_key(&node.id);
+    let text = edited.or_else(|| node.input.clone()).unwrap_or_default();
+    let can_decide = node.input.is_some();
+
+    let (status_text, status_color) = match status {
+        _ if waiting => ("Waiting for your review", "var(--text-warning)"),
+        ReviewStatus::Open => ("Not reviewed", "var(--text-muted)"),
+        ReviewStatus::Approved => ("Approved", "var(--text-success)"),
+        ReviewStatus::Rejected => ("Rejected, the nodes after it are skipped", "var(--text-error)"),
+    };
+    let button_style = "padding: 4px 10px; border: none; border-radius: 4px; cursor: pointer; font-size: 12px;";
+
+    let text_for_approve = text.clone();
+
+    rsx! {
+        NodeSocket {
+            node_id: node.id,
+            canvas_zoom,
+            on_start_connection,
+            on_connection_redirect,
+            input: true,
+            output: true
+        }
+
+        div {
+            style: "display: flex; flex-direction: column; gap: 6px; height: 100%;",
+
+            div {
+                style: "font-size: 12px; color: {status_color};",
+                "{status_text}"
+            }
+
+            textarea {
+                class: "text-box",
+                style: "flex: 1;",
+                value: "{text}",
+                placeholder: "The input to review shows up here",
+                disabled: !can_decide,
+                oninput: move |event| workflow_state.write().edit_review(node.id, event.value()),
+                onmousedown: |evt| evt.stop_propagation(),
+                onwheel: |evt| evt.stop_propagation()
+            }
+
+            div {
+                style: "display: flex; gap: 6px; justify-content: flex-end;",
+                button {
+                    style: "{button_style} background: transparent; color: var(--text-error); border: 1px solid var(--ui);",
+                    disabled: !can_decide,
+                    onmousedown: move |event| event.stop_propagation(),
+                    onclick: move |_| workflow_state.write().answer_review(node.id, ReviewDecision::Reject),
+                    "Reject"
+                }
+                button {
+                    style: "{button_style} background: var(--ui); color: var(--text-primary); font-weight: bold;",
+                    disabled: !can_decide,
+                    onmousedown: move |event| event.stop_propagation(),
+                    onclick: move |_| workflow_state.write().answer_review(node.id, ReviewDecision::Approve(text_for_approve.clone())),
+                    "Approve"
+                }
+            }
+        }
+    }
+}
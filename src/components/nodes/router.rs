@@ -0,0 +1,388 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::components::{
+    nodes::{Node, NodeSocket, text::{normalize_answer, parse_json, regex_matches}},
+    Workflow
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompareOp {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl CompareOp {
+    const ALL: [CompareOp; 5] = [CompareOp::Less, CompareOp::LessOrEqual, CompareOp::Equal, CompareOp::GreaterOrEqual, CompareOp::Greater];
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            CompareOp::Less => "<",
+            CompareOp::LessOrEqual => "<=",
+            CompareOp::Equal => "=",
+            CompareOp::GreaterOrEqual => ">=",
+            CompareOp::Greater => ">",
+        }
+    }
+
+    fn from_symbol(symbol: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|op| op.symbol() == symbol)
+    }
+
+    fn holds(&self, left: f64, right: f64) -> bool {
+        match self {
+            CompareOp::Less => left < right,
+            CompareOp::LessOrEqual => left <= right,
+            CompareOp::Equal => left == right,
+            CompareOp::GreaterOrEqual => left >= right,
+            CompareOp::Greater => left > right,
+        }
+    }
+}
+
+/// Decides whether a Router passes its input on through one of its outgoing connections,
+/// a connection without a rule always does
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RouteRule {
+    Contains { text: String },
+    Regex { pattern: String },
+    /// A field of the input parsed as JSON, `field` is a dotted path like `ticket.priority`
+    JsonEquals { field: String, value: String },
+    /// The input read as a number
+    Compare { op: CompareOp, value: f64 },
+    /// The answer of an upstream classifier model, ignoring case and surrounding punctuation
+    Label { label: String },
+    /// Taken when none of the router's other rules match
+    Otherwise,
+}
+
+impl RouteRule {
+    /// Kinds offered in the rule picker, `None` stands for a connection without a rule
+    const KINDS: [(&'static str, &'static str); 7] = [
+        ("always", "Always"),
+        ("contains", "Contains"),
+        ("regex", "Regex"),
+        ("json", "JSON field"),
+        ("compare", "Number"),
+        ("label", "Label"),
+        ("otherwise", "Otherwise"),
+    ];
+
+    fn kind(rule: Option<&RouteRule>) -> &'static str {
+        match rule {
+            None => "always",
+            Some(RouteRule::Contains { .. }) => "contains",
+            Some(RouteRule::Regex { .. }) => "regex",
+            Some(RouteRule::JsonEquals { .. }) => "json",
+            Some(RouteRule::Compare { .. }) => "compare",
+            Some(RouteRule::Label { .. }) => "label",
+            Some(RouteRule::Otherwise) => "otherwise",
+        }
+    }
+
+    fn of_kind(kind: &str) -> Option<RouteRule> {
+        match kind {
+            "contains" => Some(RouteRule::Contains { text: String::new() }),
+            "regex" => Some(RouteRule::Regex { pattern: String::new() }),
+            "json" => Some(RouteRule::JsonEquals { field: String::new(), value: String::new() }),
+            "compare" => Some(RouteRule::Compare { op: CompareOp::Equal, value: 0.0 }),
+            "label" => Some(RouteRule::Label { label: String::new() }),
+            "otherwise" => Some(RouteRule::Otherwise),
+            _ => None,
+        }
+    }
+
+    /// Whether the rule matches the router's input, `Otherwise` is decided by the router
+    pub fn matches(&self, input: &str) -> bool {
+        match self {
+            RouteRule::Contains { text } => !text.is_empty() && input.contains(text.as_str()),
+            RouteRule::Regex { pattern } => regex_matches(pattern, input),
+            RouteRule::JsonEquals { field, value } => json_field(input, field).is_some_and(|found| match found {
+                serde_json::Value::String(text) => text == *value,
+                other => serde_json::from_str::<serde_json::Value>(value).is_ok_and(|expected| expected == other),
+            }),
+            RouteRule::Compare { op, value } => input.trim().parse::<f64>().is_ok_and(|number| op.holds(number, *value)),
+            RouteRule::Label { label } => !label.trim().is_empty() && normalize_answer(input) == normalize_answer(label),
+            RouteRule::Otherwise => false,
+        }
+    }
+}
+
+/// Look up a dotted path in the input parsed as JSON
+fn json_field(input: &str, field: &str) -> Option<serde_json::Value> {
+    let json = parse_json::<serde_json::Value>(input)?;
+    field.split('.')
+        .filter(|key| !key.is_empty())
+        .try_fold(json, |value, key| match value {
+            serde_json::Value::Array(items) => items.into_iter().nth(key.parse().ok()?),
+            serde_json::Value::Object(mut map) => map.remove(key),
+            _ => None,
+        })
+}
+
+/// An outgoing connection of a Router node and whether the current input takes it
+#[derive(Clone, Debug, PartialEq)]
+pub struct RouterOutput {
+    pub connection_id: usize,
+    pub target_title: String,
+    pub rule: Option<RouteRule>,
+    pub taken: bool,
+}
+
+#[component]
+pub fn RouterNode(
+    node: Node,
+    workflow_state: Signal<Workflow>,
+    canvas_zoom: f64,
+    on_start_connection: EventHandler<(usize, String, Event<MouseData>)>,
+    on_connection_redirect: EventHandler<(usize, String, Event<MouseData>)>,
+) -> Element {
+    let outputs = use_memo(move || workflow_state.read().router_outputs(node.id));
+    let input = node.input.clone().unwrap_or_default();
+
+    rsx! {
+        NodeSocket {
+            node_id: node.id,
+            canvas_zoom,
+            on_start_connection,
+            on_connection_redirect,
+            input: true,
+            output: true
+        }
+
+        div {
+            style: "display: flex; flex-direction: column; gap: 6px; height: 100%; overflow-y: auto;",
+            onwheel: move |event| event.stop_propagation(),
+
+            div {
+                style: "font-size: 12px; color: var(--text-secondary); max-height: 48px; overflow: hidden;
+                        text-overflow: ellipsis; white-space: pre-wrap; word-break: break-word;",
+                if input.is_empty() { "No input yet" } else { "{input}" }
+            }
+
+            if outputs.read().is_empty() {
+                div {
+                    style: "font-size: 12px; color: var(--text-muted);",
+                    "Connect the output to add routes"
+                }
+            }
+
+            for output in outputs() {
+                RouteEditor { key: "{output.connection_id}", output, workflow_state }
+            }
+        }
+    }
+}
+
+#[component]
+fn RouteEditor(output: RouterOutput, workflow_state: Signal<Workflow>) -> Element {
+    let connection_id = output.connection_id;
+    let mut set_rule = move |rule: Option<RouteRule>| workflow_state.write().set_route_rule(connection_id, rule);
+    let field_style = "padding: 2px 6px; border: none; border-radius: 4px; background-color: var(--bg-alt);
+                       color: var(--text-primary); font-size: 12px; box-sizing: border-box; min-width: 0; flex: 1;";
+    let invalid_pattern = match &output.rule {
+        Some(RouteRule::Regex { pattern }) => regex::Regex::new(pattern).err().map(|e| e.to_string()),
+        _ => None,
+    };
+
+    rsx! {
+        div {
+            style: "display: flex; flex-direction: column; gap: 4px; padding: 4px; border-radius: 4px; background-color: var(--bg-alt);",
+            div {
+                style: "display: flex; align-items: center; gap: 6px; font-size: 12px;",
+                span {
+                    style: format!("color: {}; font-weight: bold;", if output.taken { "var(--text-link)" } else { "var(--text-muted)" }),
+                    title: if output.taken { "The input goes this way" } else { "Skipped" },
+                    if output.taken { "●" } else { "○" }
+                }
+                span {
+                    style: "flex: 1; color: var(--text-primary); white-space: nowrap; overflow: hidden; text-overflow: ellipsis;",
+                    "→ {output.target_title}"
+                }
+                select {
+                    value: RouteRule::kind(output.rule.as_ref()),
+                    onchange: move |event| set_rule(RouteRule::of_kind(&event.value())),
+                    onmousedown: move |event| event.stop_propagation(),
+                    style: "background: var(--ui); border: none; color: var(--text-primary); font-size: 12px; cursor: pointer;",
+                    for (kind, label) in RouteRule::KINDS {
+                        option { value: kind, "{label}" }
+                    }
+                }
+            }
+
+            div {
+                style: "display: flex; gap: 4px;",
+                match output.rule.clone() {
+                    Some(RouteRule::Contains { text }) => rsx! {
+                        input {
+                            r#type: "text",
+                            style: field_style,
+                            placeholder: "text",
+                            value: "{text}",
+                            onmousedown: move |event| event.stop_propagation(),
+                            oninput: move |event| set_rule(Some(RouteRule::Contains { text: event.value() })),
+                        }
+                    },
+                    Some(RouteRule::Regex { pattern }) => rsx! {
+                        input {
+                            r#type: "text",
+                            style: field_style,
+                            placeholder: "pattern",
+                            value: "{pattern}",
+                            onmousedown: move |event| event.stop_propagation(),
+                            oninput: move |event| set_rule(Some(RouteRule::Regex { pattern: event.value() })),
+                        }
+                    },
+                    Some(RouteRule::JsonEquals { field, value }) => {
+                        let (field_for_value, value_for_field) = (field.clone(), value.clone());
+                        rsx! {
+                            input {
+                                r#type: "text",
+                                style: field_style,
+                                placeholder: "field.path",
+                                value: "{field}",
+                                onmousedown: move |event| event.stop_propagation(),
+                                oninput: move |event| set_rule(Some(RouteRule::JsonEquals { field: event.value(), value: value_for_field.clone() })),
+                            }
+                            input {
+                                r#type: "text",
+                                style: field_style,
+                                placeholder: "value",
+                                value: "{value}",
+                                onmousedown: move |event| event.stop_propagation(),
+                                oninput: move |event| set_rule(Some(RouteRule::JsonEquals { field: field_for_value.clone(), value: event.value() })),
+                            }
+                        }
+                    },
+                    Some(RouteRule::Compare { op, value }) => rsx! {
+                        select {
+                            value: op.symbol(),
+                            onchange: move |event| {
+                                if let Some(op) = CompareOp::from_symbol(&event.value()) {
+                                    set_rule(Some(RouteRule::Compare { op, value }));
+                                }
+                            },
+                            onmousedown: move |event| event.stop_propagation(),
+                            style: "background: var(--ui); border: none; color: var(--text-primary); font-size: 12px; cursor: pointer;",
+                            for op in CompareOp::ALL {
+                                option { value: op.symbol(), "{op.symbol()}" }
+                            }
+                        }
+                        input {
+                            r#type: "number",
+                            style: field_style,
+                            value: "{value}",
+                            onmousedown: move |event| event.stop_propagation(),
+                            oninput: move |event| {
+                                if let Ok(value) = event.value().trim().parse() {
+                                    set_rule(Some(RouteRule::Compare { op, value }));
+                                }
+                            },
+                        }
+                    },
+                    Some(RouteRule::Label { label }) => rsx! {
+                        input {
+                            r#type: "text",
+                            style: field_style,
+                            placeholder: "label",
+                            value: "{label}",
+                            onmousedown: move |event| event.stop_propagation(),
+                            oninput: move |event| set_rule(Some(RouteRule::Label { label: event.value() })),
+                        }
+                    },
+                    Some(RouteRule::Otherwise) | None => rsx! {},
+                }
+            }
+
+            if let Some(error) = invalid_pattern {
+                div {
+                    style: "color: var(--text-warning); font-size: 11px;",
+                    "{error}"
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::{connections::{INPUT_PORT, OUTPUT_PORT}, nodes::NodeType};
+
+    #[test]
+    fn contains_and_regex() {
+        assert!(RouteRule::Contains { text: "urgent".to_string() }.matches("This is urgent!"));
+        assert!(!RouteRule::Contains { text: String::new() }.matches("anything"));
+        assert!(RouteRule::Regex { pattern: r"^\d+$".to_string() }.matches("42"));
+        assert!(!RouteRule::Regex { pattern: "(".to_string() }.matches("("));
+    }
+
+    #[test]
+    fn json_field_paths() {
+        let rule = |field: &str, value: &str| RouteRule::JsonEquals { field: field.to_string(), value: value.to_string() };
+        let input = r#"{"ticket": {"priority": "high", "tags": ["a", "b"], "count": 3, "open": true}}"#;
+        assert!(rule("ticket.priority", "high").matches(input));
+        assert!(rule("ticket.tags.1", "b").matches(input));
+        assert!(rule("ticket.count", "3").matches(input));
+        assert!(rule("ticket.open", "true").matches(input));
+        assert!(!rule("ticket.count", "\"3\"").matches(input));
+        assert!(!rule("ticket.missing", "high").matches(input));
+        assert!(!rule("ticket.tags.9", "a").matches(input));
+    }
+
+    #[test]
+    fn json_wrapped_in_other_text() {
+        let rule = RouteRule::JsonEquals { field: "label".to_string(), value: "spam".to_string() };
+        assert!(rule.matches("Sure, here it is:\n```json\n{\"label\": \"spam\"}\n```"));
+        assert!(!rule.matches("{\"label\": \"spam\"")); // never closed
+        assert!(!rule.matches("not json at all"));
+    }
+
+    #[test]
+    fn compare_numbers() {
+        let rule = |op: CompareOp, value: f64| RouteRule::Compare { op, value };
+        assert!(rule(CompareOp::Greater, 0.5).matches(" 0.8\n"));
+        assert!(rule(CompareOp::LessOrEqual, 3.0).matches("3"));
+        assert!(!rule(CompareOp::Equal, 1.0).matches("one"));
+    }
+
+    #[test]
+    fn labels_ignore_case_and_punctuation() {
+        let rule = RouteRule::Label { label: "Billing".to_string() };
+        assert!(rule.matches("billing."));
+        assert!(rule.matches("  \"BILLING\"  "));
+        assert!(!rule.matches("Not billing"));
+        assert!(!RouteRule::Label { label: " ".to_string() }.matches(""));
+        assert!(!RouteRule::Otherwise.matches("anything"));
+    }
+
+    #[test]
+    fn otherwise_is_taken_when_no_other_rule_matches() {
+        let mut workflow = Workflow::default();
+        workflow.nodes.clear();
+        workflow.connections.clear();
+        let router = workflow.add_node(NodeType::Router {}, 0.0, 0.0);
+        let target = |workflow: &mut Workflow| workflow.add_node(NodeType::Template { template: String::new() }, 0.0, 0.0);
+        let (first, second, fallback) = (target(&mut workflow), target(&mut workflow), target(&mut workflow));
+        let connect = |workflow: &mut Workflow, to: usize, rule: RouteRule| {
+            let conn_id = workflow.add_connection(router, OUTPUT_PORT, to, INPUT_PORT).unwrap();
+            workflow.set_route_rule(conn_id, Some(rule));
+            conn_id
+        };
+        let first = connect(&mut workflow, first, RouteRule::Contains { text: "a".to_string() });
+        let second = connect(&mut workflow, second, RouteRule::Contains { text: "b".to_string() });
+        let fallback = connect(&mut workflow, fallback, RouteRule::Otherwise);
+
+        let taken = |workflow: &mut Workflow, input: &str| {
+            workflow.nodes.get_mut(&router).unwrap().output = Some(input.to_string());
+            [first, second, fallback].map(|conn_id| workflow.is_route_taken(&workflow.connections[&conn_id]))
+        };
+        assert_eq!(taken(&mut workflow, "a"), [true, false, false]);
+        assert_eq!(taken(&mut workflow, "ab"), [true, true, false]);
+        assert_eq!(taken(&mut workflow, "c"), [false, false, true]);
+    }
+}
@@ -0,0 +1,195 @@
+use dioxus::prelude::*;
+use crate::components::{
+    nodes::{Node, NodeType, NodeSocket},
+    Workflow
+};
+
+/// An incoming connection of a Template node, in connection order
+#[derive(Clone, Debug, PartialEq)]
+pub struct TemplateInput {
+    pub connection_id: usize,
+    pub source_title: String,
+    /// Set on the connection, lets the template use `{{name}}` instead of `{{input.2}}`
+    pub name: Option<String>,
+    /// Output of the source node, `None` until it has produced one
+    pub value: Option<String>,
+}
+
+/// Names of the `{{...}}` placeholders in a template, in order of first use
+pub fn placeholders(template: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start + 2..].find("}}") else { break };
+        let name = rest[start + 2..start + 2 + end].trim().to_string();
+        if !name.is_empty() && !names.contains(&name) {
+            names.push(name);
+        }
+        rest = &rest[start + 2 + end + 2..];
+    }
+    names
+}
+
+/// The inputs a placeholder refers to: `input` is every input, `input.2` the second one and
+/// any other name the input whose connection carries that name
+fn find_inputs<'a>(name: &str, inputs: &'a [TemplateInput]) -> Option<Vec<&'a TemplateInput>> {
+    if name == "input" {
+        return (!inputs.is_empty()).then(|| inputs.iter().collect());
+    }
+    if let Some(index) = name.strip_prefix("input.").and_then(|index| index.parse::<usize>().ok()) {
+        return index.checked_sub(1).and_then(|index| inputs.get(index)).map(|input| vec![input]);
+    }
+    inputs.iter()
+        .find(|input| input.name.as_deref() == Some(name))
+        .map(|input| vec![input])
+}
+
+/// Placeholders that no incoming connection provides
+pub fn unknown_placeholders(template: &str, inputs: &[TemplateInput]) -> Vec<String> {
+    placeholders(template)
+        .into_iter()
+        .filter(|name| find_inputs(name, inputs).is_none())
+        .collect()
+}
+
+/// Fill the placeholders of a template, unknown placeholders and inputs without output yet
+/// are left empty
+pub fn render_template(template: &str, inputs: &[TemplateInput]) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start + 2..].find("}}") else { break };
+        rendered.push_str(&rest[..start]);
+        let name = rest[start + 2..start + 2 + end].trim();
+        if let Some(found) = find_inputs(name, inputs) {
+            let values: Vec<&str> = found.iter().filter_map(|input| input.value.as_deref()).collect();
+            rendered.push_str(&values.join("\n\n"));
+        }
+        rest = &rest[start + 2 + end + 2..];
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+#[component]
+pub fn TemplateNode(
+    node: Node,
+    workflow_state: Signal<Workflow>,
+    canvas_zoom: f64,
+    on_start_connection: EventHandler<(usize, String, Event<MouseData>)>,
+    on_connection_redirect: EventHandler<(usize, String, Event<MouseData>)>,
+) -> Element {
+    let inputs = use_memo(move || workflow_state.read().template_inputs(node.id));
+    let template = match &node.node_type {
+        NodeType::Template { template } => template.clone(),
+        _ => String::new(),
+    };
+    let unknown = unknown_placeholders(&template, &inputs.read());
+
+    rsx! {
+        NodeSocket {
+            node_id: node.id,
+            canvas_zoom,
+            on_start_connection,
+            on_connection_redirect,
+            input: true,
+            output: true
+        }
+
+        div {
+            style: "display: flex; flex-direction: column; gap: 6px; height: 100%;",
+
+            textarea {
+                class: "text-box",
+                style: "flex: 1;",
+                value: "{template}",
+                placeholder: "Summarize {{{{doc}}}} in the style of {{{{input.2}}}}",
+                oninput: move |event| workflow_state.write().update_template(node.id, event.value()),
+                onmousedown: |evt| evt.stop_propagation(),
+                onwheel: |evt| evt.stop_propagation()
+            }
+
+            div {
+                style: "display: flex; flex-direction: column; gap: 4px; max-height: 40%; overflow-y: auto;",
+                onwheel: move |event| event.stop_propagation(),
+                for (index, input) in inputs().into_iter().enumerate() {
+                    div {
+                        key: "{input.connection_id}",
+                        style: "display: flex; align-items: center; gap: 6px; font-size: 12px; color: var(--text-primary);",
+                        span {
+                            style: "white-space: nowrap; overflow: hidden; text-overflow: ellipsis; flex: 1;",
+                            title: "Available as {{{{input.{index + 1}}}}}",
+                            "{index + 1}. {input.source_title}"
+                        }
+                        input {
+                            r#type: "text",
+                            value: input.name.clone().unwrap_or_default(),
+                            placeholder: "name",
+                            oninput: move |event| workflow_state.write().rename_connection(input.connection_id, event.value()),
+                            onmousedown: move |event| event.stop_propagation(),
+                            style: "padding: 2px 6px; border: none; border-radius: 4px; background-color: var(--bg-alt);
+                                    color: var(--text-primary); width: 45%; box-sizing: border-box; font-size: 12px;"
+                        }
+                    }
+                }
+            }
+
+            for name in unknown {
+                div {
+                    key: "{name}",
+                    style: "color: var(--text-warning); font-size: 12px;",
+                    "No input named {{{{{name}}}}}"
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(connection_id: usize, name: Option<&str>, value: Option<&str>) -> TemplateInput {
+        TemplateInput {
+            connection_id,
+            source_title: format!("Node {}", connection_id),
+            name: name.map(str::to_string),
+            value: value.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn placeholders_are_listed_once_in_order() {
+        assert_eq!(placeholders("{{ b }} {{a}} {{b}} {{}}"), vec!["b", "a"]);
+        assert_eq!(placeholders("no placeholders"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn unterminated_placeholder_is_kept_as_text() {
+        let inputs = [input(1, Some("doc"), Some("text"))];
+        assert_eq!(placeholders("{{doc}} and {{rest"), vec!["doc"]);
+        assert_eq!(render_template("{{doc}} and {{rest", &inputs), "text and {{rest");
+    }
+
+    #[test]
+    fn inputs_are_found_by_position_and_name() {
+        let inputs = [input(1, None, Some("first")), input(2, Some("style"), Some("second"))];
+        assert_eq!(render_template("{{input.1}}|{{style}}|{{input.2}}", &inputs), "first|second|second");
+        assert_eq!(render_template("{{input}}", &inputs), "first\n\nsecond");
+    }
+
+    #[test]
+    fn unknown_and_out_of_range_placeholders_are_empty() {
+        let inputs = [input(1, None, Some("first"))];
+        assert_eq!(render_template("[{{input.0}}][{{input.2}}][{{missing}}]", &inputs), "[][][]");
+        assert_eq!(unknown_placeholders("{{input.0}} {{input.1}} {{missing}}", &inputs), vec!["input.0", "missing"]);
+        assert_eq!(unknown_placeholders("{{input}}", &[]), vec!["input"]);
+    }
+
+    #[test]
+    fn inputs_without_output_are_left_empty() {
+        let inputs = [input(1, Some("doc"), None), input(2, None, Some("second"))];
+        assert_eq!(render_template("<{{doc}}>", &inputs), "<>");
+        assert_eq!(render_template("{{input}}", &inputs), "second");
+    }
+}
@@ -0,0 +1,26 @@
+use serde::de::DeserializeOwned;
+
+/// Whether `pattern` matches `text`. An invalid pattern never matches, the nodes using one show
+/// the error next to it
+pub fn regex_matches(pattern: &str, text: &str) -> bool {
+    regex::Regex::new(pattern).is_ok_and(|regex| regex.is_match(text))
+}
+
+/// Parse JSON out of a model answer. Models often wrap JSON in explanations or code fences, so
+/// when the whole text doesn't parse the outermost `{…}` and then `[…]` are tried
+pub fn parse_json<T: DeserializeOwned>(text: &str) -> Option<T> {
+    let outermost = |open: char, close: char| {
+        let start = text.find(open)?;
+        let end = text.rfind(close)?;
+        serde_json::from_str(text.get(start..=end)?).ok()
+    };
+    serde_json::from_str(text.trim()).ok()
+        .or_else(|| outermost('{', '}'))
+        .or_else(|| outermost('[', ']'))
+}
+
+/// An answer reduced to what counts when comparing it to a label: trimmed, lowercase and without
+/// surrounding punctuation
+pub fn normalize_answer(text: &str) -> String {
+    text.trim().trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase()
+}
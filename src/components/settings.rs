@@ -1,5 +1,9 @@
 use dioxus::prelude::*;
-use crate::llm::ApiManager;
+use crate::{
+    llm::{ApiManager, ConcurrencyConfig, EndpointConfig, LLMProvider, OpenAIClient},
+    file_manager
+};
+
 #[component]
 pub fn SettingsPopup(
     popup_open: Signal<bool>,
@@ -37,6 +41,32 @@ pub fn SettingsPopup(
                         onclick: move |_| selected_section.set("api"),
                         "API Keys"
                     }
+                    
+                    button {
+                        style: format!("padding: 12px 16px; text-align: left; border: none; 
+                                       border-radius: 6px; cursor: pointer; background: {}; 
+                                       color: var(--text-primary);",
+                                     if *selected_section.read() == "providers" { 
+                                         "var(--ui)" 
+                                     } else { 
+                                         "transparent" 
+                                     }),
+                        onclick: move |_| selected_section.set("providers"),
+                        "Providers"
+                    }
+                    
+                    button {
+                        style: format!("padding: 12px 16px; text-align: left; border: none; 
+                                       border-radius: 6px; cursor: pointer; background: {}; 
+                                       color: var(--text-primary);",
+                                     if *selected_section.read() == "cache" { 
+                                         "var(--ui)" 
+                                     } else { 
+                                         "transparent" 
+                                     }),
+                        onclick: move |_| selected_section.set("cache"),
+                        "Cache"
+                    }
                 }
                 
                 // Content area
@@ -45,7 +75,11 @@ pub fn SettingsPopup(
                     
                     if *selected_section.read() == "api" {
                         ApiKeysSection {}
-                    } 
+                    } else if *selected_section.read() == "providers" {
+                        ProvidersSection {}
+                    } else if *selected_section.read() == "cache" {
+                        CacheSection {}
+                    }
                 }
             }
         }
@@ -55,8 +89,8 @@ pub fn SettingsPopup(
 #[component]
 fn ApiKeysSection() -> Element {
     let mut anthropic_key = use_signal (String::new);
-    // let mut openai_key = use_signal(String::new);
-    // let mut google_key = use_signal(String::new);
+    let mut openai_key = use_signal(String::new);
+    let mut google_key = use_signal(String::new);
     let mut save_status = use_signal(String::new);
     
     // Check if keys exist without loading their values
@@ -86,16 +120,16 @@ fn ApiKeysSection() -> Element {
                 errors.push(format!("Anthropic: {}", e));
             }
         }
-        // if !openai_key().is_empty() {
-        //     if let Err(e) = key_manager.save_openai_key(&openai_key()) {
-        //         errors.push(format!("OpenAI: {}", e));
-        //     }
-        // }
-        // if !google_key().is_empty() {
-        //     if let Err(e) = key_manager.save_google_key(&google_key()) {
-        //         errors.push(format!("Google: {}", e));
-        //     }
-        // }
+        if !openai_key().is_empty() {
+            if let Err(e) = key_manager.save_openai_key(&openai_key()) {
+                errors.push(format!("OpenAI: {}", e));
+            }
+        }
+        if !google_key().is_empty() {
+            if let Err(e) = key_manager.save_google_key(&google_key()) {
+                errors.push(format!("Google: {}", e));
+            }
+        }
         if errors.is_empty() {
             save_status.set("API keys saved successfully!".to_string());
         } else {
@@ -107,42 +141,42 @@ fn ApiKeysSection() -> Element {
         div {
             style: "flex: 1; padding: 20px; display: flex; flex-direction: column; gap: 20px;",
             
-        //     div {
-        //         style: "display: flex; flex-direction: column; gap: 12px;",
-                
-        //         h4 {
-        //             style: "margin: 0; color: var(--text-primary);",
-        //             "OpenAI API Key:"
-        //         }
-                
-        //         if keys_exist().0 {
-        //             div {
-        //                 style: "display: flex; align-items: center; gap: 12px;",
-        //                 span {
-        //                     style: "color: var(--text-secondary);",
-        //                     "API key is configured"
-        //                 }
-        //                 button {
-        //                     style: "padding: 6px 12px; background: transparent; color: var(--text-error);
-        //                            border: 1px solid var(--text-error); border-radius: 4px; cursor: pointer;",
-        //                     onclick: move |_| {
-        //                         let (_, anthropic, google) = keys_exist();
-        //                         keys_exist.set((false, anthropic, google));
-        //                     },
-        //                     "Replace"
-        //                 }
-        //             }
-        //         } else {
-        //             input {
-        //                 r#type: "password",
-        //                 placeholder: "Enter your OpenAI API key",
-        //                 style: "padding: 12px; border: 1px solid var(--ui); border-radius: 6px; 
-        //                        background: var(--bg-primary); color: var(--text-primary); font-size: 14px;",
-        //                 value: "{openai_key}",
-        //                 oninput: move |e| openai_key.set(e.value())
-        //             }
-        //         }
-        //     }
+            div {
+                style: "display: flex; flex-direction: column; gap: 12px;",
+                
+                h4 {
+                    style: "margin: 0; color: var(--text-primary);",
+                    "OpenAI API Key:"
+                }
+                
+                if keys_exist().0 {
+                    div {
+                        style: "display: flex; align-items: center; gap: 12px;",
+                        span {
+                            style: "color: var(--text-secondary);",
+                            "API key is configured"
+                        }
+                        button {
+                            style: "padding: 6px 12px; background: transparent; color: var(--text-error);
+                                   border: 1px solid var(--text-error); border-radius: 4px; cursor: pointer;",
+                            onclick: move |_| {
+                                let (_, anthropic, google) = keys_exist();
+                                keys_exist.set((false, anthropic, google));
+                            },
+                            "Replace"
+                        }
+                    }
+                } else {
+                    input {
+                        r#type: "password",
+                        placeholder: "Enter your OpenAI API key",
+                        style: "padding: 12px; border: 1px solid var(--ui); border-radius: 6px; 
+                               background: var(--bg-primary); color: var(--text-primary); font-size: 14px;",
+                        value: "{openai_key}",
+                        oninput: move |e| openai_key.set(e.value())
+                    }
+                }
+            }
             
             div {
                 style: "display: flex; flex-direction: column; gap: 12px;",
@@ -181,42 +215,42 @@ fn ApiKeysSection() -> Element {
                 }
             }
             
-            // div {
-            //     style: "display: flex; flex-direction: column; gap: 12px;",
-                
-            //     h4 {
-            //         style: "margin: 0; color: var(--text-primary);",
-            //         "Google API Key:"
-            //     }
-                
-            //     if keys_exist().2 {
-            //         div {
-            //             style: "display: flex; align-items: center; gap: 12px;",
-            //             span {
-            //                 style: "color: var(--text-secondary);",
-            //                 "API key is configured"
-            //             }
-            //             button {
-            //                 style: "padding: 6px 12px; background: transparent; color: var(--text-error);
-            //                        border: 1px solid var(--text-error); border-radius: 4px; cursor: pointer;",
-            //                 onclick: move |_| {
-            //                     let (openai, anthropic, _) = keys_exist();
-            //                     keys_exist.set((openai, anthropic, false));
-            //                 },
-            //                 "Replace"
-            //             }
-            //         }
-            //     } else {
-            //         input {
-            //             r#type: "password",
-            //             placeholder: "Enter your Google API key",
-            //             style: "padding: 12px; border: 1px solid var(--ui); border-radius: 6px; 
-            //                    background: var(--bg-primary); color: var(--text-primary); font-size: 14px;",
-            //             value: "{google_key}",
-            //             oninput: move |e| google_key.set(e.value())
-            //         }
-            //     }
-            // }
+            div {
+                style: "display: flex; flex-direction: column; gap: 12px;",
+                
+                h4 {
+                    style: "margin: 0; color: var(--text-primary);",
+                    "Google API Key:"
+                }
+                
+                if keys_exist().2 {
+                    div {
+                        style: "display: flex; align-items: center; gap: 12px;",
+                        span {
+                            style: "color: var(--text-secondary);",
+                            "API key is configured"
+                        }
+                        button {
+                            style: "padding: 6px 12px; background: transparent; color: var(--text-error);
+                                   border: 1px solid var(--text-error); border-radius: 4px; cursor: pointer;",
+                            onclick: move |_| {
+                                let (openai, anthropic, _) = keys_exist();
+                                keys_exist.set((openai, anthropic, false));
+                            },
+                            "Replace"
+                        }
+                    }
+                } else {
+                    input {
+                        r#type: "password",
+                        placeholder: "Enter your Google API key",
+                        style: "padding: 12px; border: 1px solid var(--ui); border-radius: 6px; 
+                               background: var(--bg-primary); color: var(--text-primary); font-size: 14px;",
+                        value: "{google_key}",
+                        oninput: move |e| google_key.set(e.value())
+                    }
+                }
+            }
             
             button {
                 style: "padding: 12px 24px; background: var(--ui); color: var(--text-primary);
@@ -234,4 +268,445 @@ fn ApiKeysSection() -> Element {
             }
         }
     }
-}
\ No newline at end of file
+}
+
+#[derive(Clone, PartialEq)]
+struct EndpointDraft {
+    config: EndpointConfig,
+    saved_name: Option<String>,
+    api_key: String,
+    max_parallel: String,
+    status: String,
+}
+
+/// Parse an optional parallel generation limit, empty means no limit
+fn parse_limit(value: &str) -> Result<Option<usize>, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+    match value.parse::<usize>() {
+        Ok(limit) if limit > 0 => Ok(Some(limit)),
+        _ => Err(format!("\"{}\" is not a valid number of parallel runs", value)),
+    }
+}
+
+const LIMITED_PROVIDERS: [&str; 4] = ["Ollama", "Anthropic", "OpenAI", "Google"];
+
+#[component]
+fn ProvidersSection() -> Element {
+    let initial_config = use_hook(|| file_manager::load_provider_config().unwrap_or_default());
+    let mut ollama_host = use_signal(|| initial_config.ollama.host.clone());
+    let mut ollama_timeout = use_signal(|| initial_config.ollama.timeout_secs.to_string());
+    let mut ollama_keep_alive = use_signal(|| initial_config.ollama.keep_alive.clone().unwrap_or_default());
+    let mut endpoints = use_signal(|| {
+        initial_config
+            .endpoints
+            .iter()
+            .cloned()
+            .map(|config| EndpointDraft {
+                saved_name: Some(config.name.clone()),
+                max_parallel: config.max_parallel.map(|limit| limit.to_string()).unwrap_or_default(),
+                config,
+                api_key: String::new(),
+                status: String::new(),
+            })
+            .collect::<Vec<_>>()
+    });
+    let mut max_parallel = use_signal(|| initial_config.concurrency.max_parallel.to_string());
+    let mut provider_limits = use_signal(|| {
+        let concurrency = &initial_config.concurrency;
+        [concurrency.ollama, concurrency.anthropic, concurrency.openai, concurrency.google]
+            .map(|limit| limit.map(|limit| limit.to_string()).unwrap_or_default())
+    });
+    let mut removed_endpoints = use_signal(Vec::<String>::new);
+    let mut save_status = use_signal(String::new);
+    
+    let add_endpoint = move |_| {
+        endpoints.write().push(EndpointDraft {
+            config: EndpointConfig {
+                name: String::new(),
+                base_url: "http://localhost:8080".to_string(),
+                max_parallel: None,
+            },
+            saved_name: None,
+            api_key: String::new(),
+            max_parallel: String::new(),
+            status: String::new(),
+        });
+    };
+    
+    let test_endpoint = move |index: usize| {
+        let Some(draft) = endpoints.read().get(index).cloned() else { return };
+        spawn(async move {
+            let mut client = OpenAIClient::for_endpoint(&draft.config);
+            if !draft.api_key.is_empty() {
+                client = client.with_api_key(Some(draft.api_key.clone()));
+            }
+            let status = match client.get_available_models().await {
+                Ok(models) => format!("Connected, {} models available", models.len()),
+                Err(e) => format!("Connection failed: {}", e),
+            };
+            if let Some(draft) = endpoints.write().get_mut(index) {
+                draft.status = status;
+            }
+        });
+    };
+    
+    let save_providers = move |_| {
+        if ollama_host().trim().is_empty() {
+            save_status.set("Ollama host can't be empty".to_string());
+            return;
+        }
+        let Ok(timeout_secs) = ollama_timeout().trim().parse::<u64>() else {
+            save_status.set("Ollama timeout must be a whole number of seconds".to_string());
+            return;
+        };
+        
+        let concurrency = match (max_parallel().trim().parse::<usize>(), provider_limits().map(|limit| parse_limit(&limit))) {
+            (Ok(max_parallel), [Ok(ollama), Ok(anthropic), Ok(openai), Ok(google)]) if max_parallel > 0 => {
+                ConcurrencyConfig { max_parallel, ollama, anthropic, openai, google }
+            }
+            _ => {
+                save_status.set("Parallel runs must be whole numbers above 0".to_string());
+                return;
+            }
+        };
+        
+        let drafts = endpoints();
+        let mut names = Vec::new();
+        let mut endpoint_configs = Vec::new();
+        for draft in &drafts {
+            let name = draft.config.name.trim();
+            if name.is_empty() || draft.config.base_url.trim().is_empty() {
+                save_status.set("Every endpoint needs a name and a base URL".to_string());
+                return;
+            }
+            if names.contains(&name) {
+                save_status.set(format!("Endpoint name \"{}\" is used twice", name));
+                return;
+            }
+            let max_parallel = match parse_limit(&draft.max_parallel) {
+                Ok(limit) => limit,
+                Err(e) => {
+                    save_status.set(format!("{}: {}", name, e));
+                    return;
+                }
+            };
+            names.push(name);
+            endpoint_configs.push(EndpointConfig {
+                name: name.to_string(),
+                base_url: draft.config.base_url.trim().to_string(),
+                max_parallel,
+            });
+        }
+        
+        let key_manager = match ApiManager::new() {
+            Ok(manager) => manager,
+            Err(e) => {
+                save_status.set(format!("Failed to initialize key manager: {}", e));
+                return;
+            }
+        };
+        
+        let mut errors = Vec::new();
+        for name in removed_endpoints() {
+            let _ = key_manager.delete_endpoint_key(&name);
+        }
+        for draft in &drafts {
+            let name = draft.config.name.trim();
+            if !draft.api_key.is_empty() {
+                if let Err(e) = key_manager.save_endpoint_key(name, &draft.api_key) {
+                    errors.push(format!("{}: {}", name, e));
+                }
+            } else if let Some(saved_name) = draft.saved_name.as_deref().filter(|saved| *saved != name) {
+                // Move the stored key along with a renamed endpoint
+                if let Ok(key) = key_manager.get_endpoint_key(saved_name) {
+                    if let Err(e) = key_manager.save_endpoint_key(name, &key) {
+                        errors.push(format!("{}: {}", name, e));
+                    }
+                    let _ = key_manager.delete_endpoint_key(saved_name);
+                }
+            }
+        }
+        
+        let mut config = file_manager::load_provider_config().unwrap_or_default();
+        config.ollama.host = ollama_host().trim().to_string();
+        config.ollama.timeout_secs = timeout_secs;
+        config.ollama.keep_alive = Some(ollama_keep_alive().trim().to_string()).filter(|keep_alive| !keep_alive.is_empty());
+        config.endpoints = endpoint_configs;
+        config.concurrency = concurrency;
+        if let Err(e) = file_manager::save_provider_config(&config) {
+            errors.push(format!("Config: {}", e));
+        }
+        
+        if errors.is_empty() {
+            for draft in endpoints.write().iter_mut() {
+                draft.config.name = draft.config.name.trim().to_string();
+                draft.saved_name = Some(draft.config.name.clone());
+                draft.api_key.clear();
+            }
+            removed_endpoints.write().clear();
+            save_status.set("Providers saved successfully!".to_string());
+        } else {
+            save_status.set(format!("Errors: {}", errors.join(", ")));
+        }
+    };
+    
+    rsx! {
+        div {
+            style: "flex: 1; padding: 20px; display: flex; flex-direction: column; gap: 20px; overflow-y: auto;",
+            
+            div {
+                style: "display: flex; flex-direction: column; gap: 12px;",
+                
+                h4 {
+                    style: "margin: 0; color: var(--text-primary);",
+                    "Ollama:"
+                }
+                
+                div {
+                    style: "display: grid; grid-template-columns: auto 1fr; gap: 8px 12px; align-items: center;",
+                    span { style: "color: var(--text-secondary);", "Host" }
+                    input {
+                        r#type: "text",
+                        placeholder: "http://localhost:11434",
+                        style: "padding: 8px; border: 1px solid var(--ui); border-radius: 6px; 
+                               background: var(--bg-primary); color: var(--text-primary); font-size: 14px;",
+                        value: "{ollama_host}",
+                        oninput: move |e| ollama_host.set(e.value())
+                    }
+                    span { style: "color: var(--text-secondary);", "Timeout (s)" }
+                    input {
+                        r#type: "number",
+                        placeholder: "0 disables the timeout",
+                        style: "padding: 8px; border: 1px solid var(--ui); border-radius: 6px; 
+                               background: var(--bg-primary); color: var(--text-primary); font-size: 14px;",
+                        value: "{ollama_timeout}",
+                        oninput: move |e| ollama_timeout.set(e.value())
+                    }
+                    span { style: "color: var(--text-secondary);", "Keep alive" }
+                    input {
+                        r#type: "text",
+                        placeholder: "Server default, e.g. 5m, 1h or -1",
+                        style: "padding: 8px; border: 1px solid var(--ui); border-radius: 6px; 
+                               background: var(--bg-primary); color: var(--text-primary); font-size: 14px;",
+                        value: "{ollama_keep_alive}",
+                        oninput: move |e| ollama_keep_alive.set(e.value())
+                    }
+                }
+            }
+            
+            div {
+                style: "display: flex; flex-direction: column; gap: 12px;",
+                
+                h4 {
+                    style: "margin: 0; color: var(--text-primary);",
+                    "OpenAI-compatible Endpoints:"
+                }
+                span {
+                    style: "color: var(--text-muted); font-size: 13px;",
+                    "llama.cpp server, vLLM, LM Studio, LocalAI or any other server exposing /v1/chat/completions"
+                }
+                
+                for (index, draft) in endpoints().into_iter().enumerate() {
+                    div {
+                        key: "{index}",
+                        style: "display: flex; flex-direction: column; gap: 8px; padding: 12px; 
+                               border: 1px solid var(--ui); border-radius: 6px;",
+                        
+                        div {
+                            style: "display: flex; gap: 8px;",
+                            input {
+                                r#type: "text",
+                                placeholder: "Name",
+                                style: "flex: 1; padding: 8px; border: 1px solid var(--ui); border-radius: 6px; 
+                                       background: var(--bg-primary); color: var(--text-primary); font-size: 14px;",
+                                value: "{draft.config.name}",
+                                oninput: move |e| endpoints.write()[index].config.name = e.value()
+                            }
+                            input {
+                                r#type: "text",
+                                placeholder: "Base URL, e.g. http://localhost:8080",
+                                style: "flex: 2; padding: 8px; border: 1px solid var(--ui); border-radius: 6px; 
+                                       background: var(--bg-primary); color: var(--text-primary); font-size: 14px;",
+                                value: "{draft.config.base_url}",
+                                oninput: move |e| endpoints.write()[index].config.base_url = e.value()
+                            }
+                            input {
+                                r#type: "number",
+                                placeholder: "Parallel",
+                                title: "Maximum parallel generations, empty for no limit",
+                                style: "width: 90px; padding: 8px; border: 1px solid var(--ui); border-radius: 6px; 
+                                       background: var(--bg-primary); color: var(--text-primary); font-size: 14px;",
+                                value: "{draft.max_parallel}",
+                                oninput: move |e| endpoints.write()[index].max_parallel = e.value()
+                            }
+                        }
+                        
+                        div {
+                            style: "display: flex; gap: 8px; align-items: center;",
+                            input {
+                                r#type: "password",
+                                placeholder: if draft.saved_name.is_some() { "API key (leave empty to keep the stored key)" } else { "API key (optional)" },
+                                style: "flex: 1; padding: 8px; border: 1px solid var(--ui); border-radius: 6px; 
+                                       background: var(--bg-primary); color: var(--text-primary); font-size: 14px;",
+                                value: "{draft.api_key}",
+                                oninput: move |e| endpoints.write()[index].api_key = e.value()
+                            }
+                            button {
+                                style: "padding: 6px 12px; background: transparent; color: var(--text-primary);
+                                       border: 1px solid var(--text-primary); border-radius: 4px; cursor: pointer;",
+                                onclick: move |_| test_endpoint(index),
+                                "Test"
+                            }
+                            button {
+                                style: "padding: 6px 12px; background: transparent; color: var(--text-error);
+                                       border: 1px solid var(--text-error); border-radius: 4px; cursor: pointer;",
+                                onclick: move |_| {
+                                    let removed = endpoints.write().remove(index);
+                                    if let Some(saved_name) = removed.saved_name {
+                                        removed_endpoints.write().push(saved_name);
+                                    }
+                                },
+                                "Remove"
+                            }
+                        }
+                        
+                        if !draft.status.is_empty() {
+                            span {
+                                style: "color: var(--text-secondary); font-size: 13px;",
+                                "{draft.status}"
+                            }
+                        }
+                    }
+                }
+                
+                button {
+                    style: "padding: 6px 12px; background: transparent; color: var(--text-primary);
+                           border: 1px dashed var(--text-primary); border-radius: 4px; cursor: pointer; 
+                           align-self: flex-start;",
+                    onclick: add_endpoint,
+                    "+ Add Endpoint"
+                }
+            }
+            
+            div {
+                style: "display: flex; flex-direction: column; gap: 12px;",
+                
+                h4 {
+                    style: "margin: 0; color: var(--text-primary);",
+                    "Parallel Runs:"
+                }
+                span {
+                    style: "color: var(--text-muted); font-size: 13px;",
+                    "How many model nodes a workflow run generates at the same time, leave a provider empty for no extra limit"
+                }
+                
+                div {
+                    style: "display: grid; grid-template-columns: auto 1fr; gap: 8px 12px; align-items: center;",
+                    span { style: "color: var(--text-secondary);", "All providers" }
+                    input {
+                        r#type: "number",
+                        style: "padding: 8px; border: 1px solid var(--ui); border-radius: 6px; 
+                               background: var(--bg-primary); color: var(--text-primary); font-size: 14px;",
+                        value: "{max_parallel}",
+                        oninput: move |e| max_parallel.set(e.value())
+                    }
+                    for (index, provider) in LIMITED_PROVIDERS.into_iter().enumerate() {
+                        span { style: "color: var(--text-secondary);", "{provider}" }
+                        input {
+                            r#type: "number",
+                            placeholder: "No limit",
+                            style: "padding: 8px; border: 1px solid var(--ui); border-radius: 6px; 
+                                   background: var(--bg-primary); color: var(--text-primary); font-size: 14px;",
+                            value: "{provider_limits.read()[index]}",
+                            oninput: move |e| provider_limits.write()[index] = e.value()
+                        }
+                    }
+                }
+            }
+            
+            button {
+                style: "padding: 12px 24px; background: var(--ui); color: var(--text-primary);
+                       border: none; border-radius: 6px; cursor: pointer; font-weight: 500; 
+                       align-self: flex-start;",
+                onclick: save_providers,
+                "Save Providers"
+            }
+            
+            if !save_status().is_empty() {
+                div {
+                    style: "padding: 12px; border-radius: 6px; background: var(--ui); color: var(--text-primary); font-size: 14px;",
+                    "{save_status}"
+                }
+            }
+        }
+    }
+}
+
+/// Describe the response cache for the settings, e.g. "12 responses (48.2 KB)"
+fn describe_cache_size() -> String {
+    match file_manager::response_cache_size() {
+        Ok((0, _)) => "The cache is empty".to_string(),
+        Ok((entries, bytes)) => format!(
+            "{} cached {} ({:.1} KB)",
+            entries,
+            if entries == 1 { "response" } else { "responses" },
+            bytes as f64 / 1024.0
+        ),
+        Err(e) => format!("Failed to read the cache: {}", e),
+    }
+}
+
+#[component]
+fn CacheSection() -> Element {
+    let mut cache_size = use_signal(describe_cache_size);
+    let mut clear_status = use_signal(String::new);
+    
+    let clear_cache = move |_| {
+        match file_manager::clear_response_cache() {
+            Ok(()) => clear_status.set("Cache cleared".to_string()),
+            Err(e) => clear_status.set(format!("Failed to clear the cache: {:#}", e)),
+        }
+        cache_size.set(describe_cache_size());
+    };
+    
+    rsx! {
+        div {
+            style: "flex: 1; padding: 20px; display: flex; flex-direction: column; gap: 20px;",
+            
+            div {
+                style: "display: flex; flex-direction: column; gap: 12px;",
+                
+                h4 {
+                    style: "margin: 0; color: var(--text-primary);",
+                    "Response Cache:"
+                }
+                span {
+                    style: "color: var(--text-muted); font-size: 13px;",
+                    "Model answers are reused when a node sends the same prompt with the same model and settings again"
+                }
+                span {
+                    style: "color: var(--text-secondary);",
+                    "{cache_size}"
+                }
+            }
+            
+            button {
+                style: "padding: 12px 24px; background: var(--ui); color: var(--text-primary);
+                       border: none; border-radius: 6px; cursor: pointer; font-weight: 500; 
+                       align-self: flex-start;",
+                onclick: clear_cache,
+                "Clear Cache"
+            }
+            
+            if !clear_status().is_empty() {
+                div {
+                    style: "padding: 12px; border-radius: 6px; background: var(--ui); color: var(--text-primary); font-size: 14px;",
+                    "{clear_status}"
+                }
+            }
+        }
+    }
+}
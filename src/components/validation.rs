@@ -0,0 +1,230 @@
+use dioxus::prelude::*;
+use std::collections::{HashMap, HashSet};
+use crate::components::{
+    connections::{COLLECT_PORT, FEEDBACK_PORT, INPUT_PORT},
+    nodes::{NodeType, RouteRule, unknown_placeholders},
+    workflow::Workflow
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Severity {
+    /// The workflow can't run until it is fixed
+    Error,
+    /// The workflow runs but probably not as intended
+    Warning,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidationIssue {
+    pub severity: Severity,
+    pub node_id: Option<usize>,
+    pub message: String,
+}
+
+impl ValidationIssue {
+    fn error(node_id: Option<usize>, message: String) -> Self {
+        Self { severity: Severity::Error, node_id, message }
+    }
+
+    fn warning(node_id: Option<usize>, message: String) -> Self {
+        Self { severity: Severity::Warning, node_id, message }
+    }
+}
+
+pub fn has_errors(issues: &[ValidationIssue]) -> bool {
+    issues.iter().any(|issue| issue.severity == Severity::Error)
+}
+
+impl Workflow {
+    /// Check the workflow before a run, errors come first
+    pub fn validate(&self) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+        let title = |node_id: usize| self.nodes.get(&node_id).map(|node| node.title.as_str()).unwrap_or("?");
+
+        let mut connections: Vec<_> = self.connections.values().collect();
+        connections.sort_by_key(|conn| conn.id);
+        let mut seen = HashSet::new();
+        for conn in connections {
+            let (Some(from), Some(to)) = (self.nodes.get(&conn.from_node_id), self.nodes.get(&conn.to_node_id)) else {
+                issues.push(ValidationIssue::error(None, "A connection points to a node that no longer exists".to_string()));
+                continue;
+            };
+            if !to.node_type.input_ports().contains(&conn.to_port.as_str()) {
+                issues.push(ValidationIssue::error(
+                    Some(to.id),
+                    format!("{} is connected into {}, which has no {} input", from.title, to.title, conn.to_port)
+                ));
+            }
+            if let Some(RouteRule::Regex { pattern }) = &conn.route {
+                if let Err(e) = regex::Regex::new(pattern) {
+                    issues.push(ValidationIssue::warning(
+                        Some(from.id),
+                        format!("The route of {} to {} has an invalid pattern: {}", from.title, to.title, e)
+                    ));
+                }
+            }
+            if !seen.insert((conn.from_node_id, conn.to_node_id, conn.to_port.as_str())) {
+                issues.push(ValidationIssue::warning(
+                    Some(to.id),
+                    format!("{} is connected to {} more than once", from.title, to.title)
+                ));
+            }
+        }
+
+        let looping = self.nodes_in_loops();
+        if let Some(&first) = looping.first() {
+            let titles = looping.iter().map(|&node_id| title(node_id)).collect::<Vec<_>>().join(", ");
+            issues.push(ValidationIssue::error(Some(first), format!("Connections form a loop through {}", titles)));
+        }
+
+        let map_bodies = self.map_bodies();
+        let mut nodes: Vec<_> = self.nodes.values().collect();
+        nodes.sort_by_key(|node| node.id);
+        for node in nodes {
+            let has_input_connection = |port: &str| self.connections
+                .values()
+                .any(|conn| conn.to_node_id == node.id && conn.to_port == port);
+            match &node.node_type {
+                NodeType::Model { model_name, .. } if node.needs_execution => {
+                    if model_name.is_empty() {
+                        issues.push(ValidationIssue::error(Some(node.id), format!("{} has no model selected", node.title)));
+                    }
+                    // Inputs of connected nodes only arrive during the run
+                    if !has_input_connection(INPUT_PORT) && node.prepare_prompt().is_err() {
+                        issues.push(ValidationIssue::warning(Some(node.id), format!("{} has no input, running it will fail", node.title)));
+                    }
+                }
+                NodeType::FileExport { folder_path, file_name, .. }
+                    if has_input_connection(INPUT_PORT) && (folder_path.is_none() || file_name.as_deref().is_none_or(str::is_empty)) =>
+                {
+                    issues.push(ValidationIssue::warning(Some(node.id), format!("{} has no folder or file name", node.title)));
+                }
+                NodeType::Template { template } => {
+                    let inputs = self.template_inputs(node.id);
+                    for name in unknown_placeholders(template, &inputs) {
+                        issues.push(ValidationIssue::warning(
+                            Some(node.id),
+                            format!("{} uses {{{{{}}}}} but no input provides it", node.title, name)
+                        ));
+                    }
+                }
+                NodeType::Loop { .. } if !has_input_connection(FEEDBACK_PORT) => {
+                    issues.push(ValidationIssue::warning(
+                        Some(node.id),
+                        format!("{} has nothing connected to its feedback input and will only run once", node.title)
+                    ));
+                }
+                // Items run on their own and can't stop to wait for an answer
+                NodeType::Review { .. } if map_bodies.contains(&node.id) => {
+                    issues.push(ValidationIssue::error(
+                        Some(node.id),
+                        format!("{} runs once per item of a Map node, Review nodes can only come before or after it", node.title)
+                    ));
+                }
+                NodeType::Map { .. } if !has_input_connection(COLLECT_PORT) => {
+                    issues.push(ValidationIssue::warning(
+                        Some(node.id),
+                        format!("{} has nothing connected to its collect input, so it has no results", node.title)
+                    ));
+                }
+                _ => {}
+            }
+        }
+
+        issues.sort_by_key(|issue| issue.severity == Severity::Warning);
+        issues
+    }
+
+    /// Nodes that sit on a loop of connections, sorted by id
+    fn nodes_in_loops(&self) -> Vec<usize> {
+        let mut successors: HashMap<usize, Vec<usize>> = HashMap::new();
+        // Loop nodes are meant to receive results back from the nodes after them
+        for conn in self.connections.values().filter(|conn| !conn.is_loop_back()) {
+            successors.entry(conn.from_node_id).or_default().push(conn.to_node_id);
+        }
+
+        // A node is on a loop when it can reach itself again
+        let mut looping: Vec<usize> = self.nodes
+            .keys()
+            .copied()
+            .filter(|&start| {
+                let mut visited = HashSet::new();
+                let mut stack = successors.get(&start).cloned().unwrap_or_default();
+                while let Some(node_id) = stack.pop() {
+                    if node_id == start {
+                        return true;
+                    }
+                    if visited.insert(node_id) {
+                        stack.extend(successors.get(&node_id).into_iter().flatten());
+                    }
+                }
+                false
+            })
+            .collect();
+        looping.sort_unstable();
+        looping
+    }
+}
+
+/// Issues found before a run, the run can only continue when there are no errors
+#[component]
+pub fn ValidationReport(
+    issues: Vec<ValidationIssue>,
+    workflow_state: Signal<Workflow>,
+    on_close: EventHandler<()>,
+    on_run: EventHandler<()>,
+) -> Element {
+    let can_run = !has_errors(&issues);
+
+    rsx! {
+        div {
+            class: "validation-report",
+            style: "position: absolute; top: 70px; right: 10px; z-index: 150; width: 360px; max-height: 50%;
+                   display: flex; flex-direction: column; gap: 8px; padding: 12px;
+                   background: var(--bg-alt); border: 1px solid var(--ui); border-radius: 8px;",
+            onmousedown: move |event| event.stop_propagation(),
+
+            div {
+                style: "font-weight: bold; color: var(--text-primary);",
+                if can_run { "Check before running" } else { "Workflow can't run" }
+            }
+
+            div {
+                style: "display: flex; flex-direction: column; gap: 4px; overflow-y: auto;",
+                onwheel: move |event| event.stop_propagation(),
+                for (index, issue) in issues.into_iter().enumerate() {
+                    div {
+                        key: "{index}",
+                        style: format!("font-size: 13px; padding: 4px 6px; border-radius: 4px; color: {}; cursor: {};",
+                            if issue.severity == Severity::Error { "var(--text-error)" } else { "var(--text-warning)" },
+                            if issue.node_id.is_some() { "pointer" } else { "default" }),
+                        onclick: move |_| {
+                            if let Some(node_id) = issue.node_id {
+                                workflow_state.write().selected_node_id = Some(node_id);
+                            }
+                        },
+                        "{issue.message}"
+                    }
+                }
+            }
+
+            div {
+                style: "display: flex; gap: 8px; justify-content: flex-end;",
+                button {
+                    style: "padding: 6px 12px; background: transparent; color: var(--text-primary);
+                           border: 1px solid var(--ui); border-radius: 4px; cursor: pointer;",
+                    onclick: move |_| on_close.call(()),
+                    "Close"
+                }
+                if can_run {
+                    button {
+                        style: "padding: 6px 12px; background: var(--ui); color: var(--text-primary);
+                               border: none; border-radius: 4px; cursor: pointer; font-weight: bold;",
+                        onclick: move |_| on_run.call(()),
+                        "Run anyway"
+                    }
+                }
+            }
+        }
+    }
+}
@@ -2,12 +2,30 @@ use dioxus::prelude::*;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use tokio_util::sync::CancellationToken;
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+use crate::executor::{ExecutionEvent, WorkflowExecutor};
+use crate::file_manager;
 use crate::components::{
     canvas::CanvasState,
-    nodes::{Node, NodeType, NodeComponent, ProviderType},
-    connections::{Connection, get_port_world_pos, ConnectionDrawingState, ConnectionsRenderer}
+    nodes::{ChatMessage, LoopStep, MessageRole, Node, NodeType, NodeComponent, FolderListing, ImportedFile, ProviderType, ReviewDecision, ReviewStatus, RouteRule, RouterOutput, TemplateInput, loop_should_stop, render_template},
+    connections::{Connection, get_port_world_pos, ConnectionDrawingState, ConnectionsRenderer, is_loop_back_port, FEEDBACK_PORT, INPUT_PORT, ITEM_PORT, JUDGE_PORT, OUTPUT_PORT, SYSTEM_PORT}
 };
 
+/// Which nodes a run executes, scoped runs always execute the node they start from
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RunScope {
+    /// Every node that needs execution
+    All,
+    /// Only this node
+    Node(usize),
+    /// This node and the nodes after it
+    Downstream(usize),
+    /// This node and the nodes it depends on
+    Upstream(usize),
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Workflow {
     pub nodes: HashMap<usize, Node>,
@@ -17,6 +35,18 @@ pub struct Workflow {
     pub selected_node_id: Option<usize>,
     pub dragging_node_id: Option<usize>,
     pub drawing_connection_state: ConnectionDrawingState,
+    /// Cancels the whole workflow run while one is in progress
+    #[serde(skip)]
+    pub run_token: Option<CancellationToken>,
+    /// Cancels the generation of each currently executing node
+    #[serde(skip)]
+    pub node_tokens: HashMap<usize, CancellationToken>,
+    /// Answers the Review nodes a run is waiting at
+    #[serde(skip)]
+    pub review_replies: HashMap<usize, UnboundedSender<ReviewDecision>>,
+    /// Outputs of the Model nodes currently streaming a response, put back when it doesn't finish
+    #[serde(skip)]
+    pub outputs_before_response: HashMap<usize, Option<String>>,
 }
 
 
@@ -30,12 +60,16 @@ impl Default for Workflow {
             selected_node_id: None,
             dragging_node_id: None,
             drawing_connection_state: ConnectionDrawingState::default(),
+            run_token: None,
+            node_tokens: HashMap::new(),
+            review_replies: HashMap::new(),
+            outputs_before_response: HashMap::new(),
         };
 
         // Add the default nodes
         let context_id = state.add_node(NodeType::Prompt {}, 50.0, 100.0);
-        let model_id = state.add_node(NodeType::Model { provider: ProviderType::Ollama, model_name: "".into(), messages: Vec::new(), thinking: false }, 400.0, 100.0);
-        let _ = state.add_connection(context_id, model_id);
+        let model_id = state.add_node(NodeType::model(ProviderType::Ollama), 400.0, 100.0);
+        let _ = state.add_connection(context_id, OUTPUT_PORT, model_id, INPUT_PORT);
         
         state
     }
@@ -52,8 +86,19 @@ impl Workflow {
     }
 
     pub fn remove_node(&mut self, id: usize) {
+        self.stop_node(id);
+        let target_ids: Vec<usize> = self.connections
+            .values()
+            .filter(|conn| conn.from_node_id == id)
+            .map(|conn| conn.to_node_id)
+            .collect();
         self.nodes.remove(&id);
         self.connections.retain(|_, conn| conn.from_node_id != id && conn.to_node_id != id);
+        // The nodes it fed lose part of their input
+        for target_id in target_ids {
+            self.mark_dirty(target_id);
+            self.update_node_input_from_all_sources(&target_id);
+        }
 
         if self.selected_node_id == Some(id) {
             self.selected_node_id = None;
@@ -66,32 +111,104 @@ impl Workflow {
         }
     }
 
-    pub fn add_connection(&mut self, from_node_id: usize, to_node_id: usize) -> Result<usize, String> {
+    pub fn add_connection(&mut self, from_node_id: usize, from_port: &str, to_node_id: usize, to_port: &str) -> Result<usize, String> {
         if from_node_id == to_node_id {
             return Err("Cannot connect a node to itself".to_string());
         }
+        let Some(target) = self.nodes.get(&to_node_id) else {
+            return Err("Target node no longer exists".to_string());
+        };
+        if !target.node_type.input_ports().contains(&to_port) {
+            return Err(format!("{} has no {} input", target.title, to_port));
+        }
+        if self.connections.values().any(|conn| conn.from_node_id == from_node_id && conn.from_port == from_port && conn.to_node_id == to_node_id && conn.to_port == to_port) {
+            return Err("These nodes are already connected".to_string());
+        }
+        // Feeding results back into a Loop or Map node is the one way to close a loop
+        if !is_loop_back_port(to_port) && self.downstream_nodes(to_node_id).contains(&from_node_id) {
+            return Err("Connection would create a loop".to_string());
+        }
 
+        // New inputs go after the existing ones of the port
+        let order = self.port_connections(to_node_id, to_port).last().map_or(0, |conn| conn.order + 1);
         let conn_id = self.next_connection_id;
-        self.connections.insert(conn_id, Connection { id: conn_id, from_node_id, to_node_id });
+        self.connections.insert(conn_id, Connection {
+            id: conn_id,
+            from_node_id,
+            from_port: from_port.to_string(),
+            to_node_id,
+            to_port: to_port.to_string(),
+            order,
+            name: None,
+            route: None,
+        });
         self.next_connection_id += 1;
         
+        self.mark_dirty(to_node_id);
         self.update_node_input_from_all_sources(&to_node_id);
-        // Mark target node as needing execution
-        if let Some(node) = self.nodes.get_mut(&to_node_id) {
-            node.needs_execution = true;
-        }
         Ok(conn_id)
     }
 
-    fn remove_connection_by_target_node(&mut self, target_node_id: usize) {
+    /// Every node fed by `node_id`, directly or through other nodes of any type. Results fed
+    /// back into a Loop node don't count
+    pub fn downstream_nodes(&self, node_id: usize) -> HashSet<usize> {
+        let mut downstream = HashSet::new();
+        let mut stack = vec![node_id];
+        while let Some(current) = stack.pop() {
+            for conn in self.connections.values().filter(|conn| conn.from_node_id == current && !conn.is_loop_back()) {
+                if downstream.insert(conn.to_node_id) {
+                    stack.push(conn.to_node_id);
+                }
+            }
+        }
+        downstream
+    }
+
+    /// Every node feeding `node_id`, directly or through other nodes of any type. Results fed
+    /// back into a Loop node don't count
+    pub fn upstream_nodes(&self, node_id: usize) -> HashSet<usize> {
+        let mut upstream = HashSet::new();
+        let mut stack = vec![node_id];
+        while let Some(current) = stack.pop() {
+            for conn in self.connections.values().filter(|conn| conn.to_node_id == current && !conn.is_loop_back()) {
+                if upstream.insert(conn.from_node_id) {
+                    stack.push(conn.from_node_id);
+                }
+            }
+        }
+        upstream
+    }
+
+    /// A node's settings or input changed, it and everything after it has to run again, as does
+    /// a Map node whose body it is part of
+    pub fn mark_dirty(&mut self, node_id: usize) {
+        let mut stale = self.downstream_nodes(node_id);
+        stale.insert(node_id);
+        let maps: Vec<usize> = self.nodes
+            .values()
+            .filter(|node| matches!(node.node_type, NodeType::Map { .. }) && !node.needs_execution && !stale.contains(&node.id))
+            .map(|node| node.id)
+            .filter(|&map_id| !self.body_nodes(map_id).is_disjoint(&stale))
+            .collect();
+        for id in stale {
+            if let Some(node) = self.nodes.get_mut(&id) {
+                node.needs_execution = true;
+                node.skipped = false;
+            }
+        }
+        for map_id in maps {
+            self.mark_dirty(map_id);
+        }
+    }
+
+    fn remove_connection_by_target_node(&mut self, target_node_id: usize, port: &str) {
         let conn_id_to_remove = self.connections.iter()
-            .find_map(|(id, conn)| if conn.to_node_id == target_node_id { Some(*id) } else { None });
+            .find_map(|(id, conn)| if conn.to_node_id == target_node_id && conn.to_port == port { Some(*id) } else { None });
 
         if let Some(id) = conn_id_to_remove {
             self.connections.remove(&id);
-            if let Some(node) = self.nodes.get_mut(&target_node_id) {
-                node.input = None;
-            }
+            self.mark_dirty(target_node_id);
+            self.update_node_input_from_all_sources(&target_node_id);
         }
     }
 
@@ -115,66 +232,82 @@ impl Workflow {
     }
 
     pub fn end_dragging_node(&mut self) {
-        if let Some(dragged_node_id) = self.dragging_node_id {
-            self.dragging_node_id = None;
-            // Update inputs of nodes that receive from the dragged node
-            self.propagate_output_to_connected_nodes(&dragged_node_id);
-        }
+        self.dragging_node_id = None;
     }
     
-    pub fn get_input_order_number(&self, node_id: usize, target_node_id: usize) -> Option<usize> {
-        // Get all source nodes for the target, sorted by visual position
-        let mut source_nodes: Vec<(usize, f64, f64)> = self.connections
+    /// Connections into one port of a node, in the order their outputs are combined
+    pub fn port_connections(&self, node_id: usize, port: &str) -> Vec<&Connection> {
+        let mut connections: Vec<&Connection> = self.connections
             .values()
-            .filter(|conn| conn.to_node_id == target_node_id)
-            .filter_map(|conn| {
-                self.nodes.get(&conn.from_node_id).map(|node| 
-                    (conn.from_node_id, node.position_y, node.position_x)
-                )
-            })
+            .filter(|conn| conn.to_node_id == node_id && conn.to_port == port)
             .collect();
+        // Workflows saved before the order existed keep the order the connections were made in
+        connections.sort_by_key(|conn| (conn.order, conn.id));
+        connections
+    }
+    
+    /// The connection from `node_id` into `target_node_id` and its 1-based place among the
+    /// inputs of that port, `None` when it is the port's only input
+    pub fn get_input_order_number(&self, node_id: usize, target_node_id: usize) -> Option<(usize, usize)> {
+        let port = &self.connections
+            .values()
+            .find(|conn| conn.from_node_id == node_id && conn.to_node_id == target_node_id)?
+            .to_port;
         
-        // Only show numbers if there are multiple inputs
-        if source_nodes.len() <= 1 {
+        let connections = self.port_connections(target_node_id, port);
+        if connections.len() <= 1 {
             return None;
         }
+        connections.iter()
+            .position(|conn| conn.from_node_id == node_id)
+            .map(|index| (connections[index].id, index + 1))
+    }
+    
+    /// Move a connection one place earlier or later among the inputs of its port
+    pub fn move_connection(&mut self, conn_id: usize, earlier: bool) {
+        let Some(conn) = self.connections.get(&conn_id) else { return };
+        let (target_node_id, port) = (conn.to_node_id, conn.to_port.clone());
+        let mut ordered: Vec<usize> = self.port_connections(target_node_id, &port).iter().map(|conn| conn.id).collect();
+        let Some(index) = ordered.iter().position(|&id| id == conn_id) else { return };
+        let other = if earlier { index.checked_sub(1) } else { Some(index + 1).filter(|&i| i < ordered.len()) };
+        let Some(other) = other else { return };
         
-        // Sort by visual position
-        source_nodes.sort_by(|a, b| {
-            a.1.partial_cmp(&b.1)
-                .unwrap_or(std::cmp::Ordering::Equal)
-                .then_with(|| a.2.partial_cmp(&b.2).unwrap_or(std::cmp::Ordering::Equal))
-        });
-        
-        // Find the position of our node_id in the sorted list
-        source_nodes.iter()
-            .position(|(id, _, _)| *id == node_id)
-            .map(|pos| pos + 1) // 1-based numbering
+        ordered.swap(index, other);
+        for (order, id) in ordered.into_iter().enumerate() {
+            if let Some(conn) = self.connections.get_mut(&id) {
+                conn.order = order;
+            }
+        }
+        self.mark_dirty(target_node_id);
+        self.update_node_input_from_all_sources(&target_node_id);
     }
 
-    pub fn start_drawing_connection(&mut self, source_node_id: usize, port_page_x: f64, port_page_y: f64, canvas: &CanvasState) {
+    pub fn start_drawing_connection(&mut self, source_node_id: usize, source_port: &str, port_page_x: f64, port_page_y: f64, canvas: &CanvasState) {
         if self.nodes.contains_key(&source_node_id) {
             let source_port_world_pos = canvas.page_to_world_coords(port_page_x, port_page_y);
             self.drawing_connection_state = ConnectionDrawingState {
                 active: true,
                 source_node_id,
+                source_port: source_port.to_string(),
                 source_port_world_pos,
                 current_mouse_world_pos: source_port_world_pos,
                 target_node_id: None,
+                target_port: None,
             };
         }
     }
     
-    pub fn redirect_connection(&mut self, target_node_id: usize) {
-        let Some((_, connection)) = self.connections.iter().find(|(_, conn)| conn.to_node_id == target_node_id) else {
+    pub fn redirect_connection(&mut self, target_node_id: usize, port: &str) {
+        let Some((_, connection)) = self.connections.iter().find(|(_, conn)| conn.to_node_id == target_node_id && conn.to_port == port) else {
             return;
         };
         let source_node_id = connection.from_node_id;
+        let source_port = connection.from_port.clone();
         
         let current_mouse_pos = if self.drawing_connection_state.active {
             self.drawing_connection_state.current_mouse_world_pos
         } else if let Some(target_node) = self.nodes.get(&target_node_id) {
-            get_port_world_pos(target_node, "input")
+            get_port_world_pos(target_node, port)
         } else {
             (0.0, 0.0)
         };
@@ -182,15 +315,17 @@ impl Workflow {
         let Some(source_node) = self.nodes.get(&source_node_id) else {
             return;
         };
-        let source_port_world_pos = get_port_world_pos(source_node, "output");
+        let source_port_world_pos = get_port_world_pos(source_node, &source_port);
         self.drawing_connection_state = ConnectionDrawingState {
             active: true,
             source_node_id,
+            source_port,
             source_port_world_pos,
             current_mouse_world_pos: current_mouse_pos,
             target_node_id: None,
+            target_port: None,
         };
-        self.remove_connection_by_target_node(target_node_id);
+        self.remove_connection_by_target_node(target_node_id, port);
     }
 
     pub fn update_drawing_connection(&mut self, mouse_page_x: f64, mouse_page_y: f64, canvas: &CanvasState) {
@@ -199,63 +334,404 @@ impl Workflow {
     }
     
     pub fn update_node_output(&mut self, node_id: usize, new_output: String) {
-        if let Some(node) = self.nodes.get_mut(&node_id) {
-            node.output = Some(new_output);
-            node.needs_execution = false;
+        let Some(node) = self.nodes.get_mut(&node_id) else { return };
+        node.needs_execution = false;
+        // An unchanged output leaves the nodes after it as they are
+        if node.output.as_ref() == Some(&new_output) {
+            return;
         }
-        self.propagate_output_to_connected_nodes(&node_id);
+        node.output = Some(new_output);
+        self.propagate_output_to_connected_nodes(&node_id, OUTPUT_PORT);
     }
     
-    fn propagate_output_to_connected_nodes(&mut self, source_node_id: &usize) {
-        // Find all connections where this node is the source
-        let target_node_ids: Vec<usize> = self.connections
+    fn propagate_output_to_connected_nodes(&mut self, source_node_id: &usize, source_port: &str) {
+        // Find all connections where this output of the node is the source
+        let targets: Vec<(usize, bool)> = self.connections
             .values()
-            .filter(|conn| &conn.from_node_id == source_node_id)
-            .map(|conn| conn.to_node_id)
+            .filter(|conn| &conn.from_node_id == source_node_id && conn.from_port == source_port)
+            .map(|conn| (conn.to_node_id, conn.is_loop_back()))
             .collect();
         
-        // Update input of all connected target nodes
-        for target_id in target_node_ids {
-            self.update_node_input_from_all_sources(&target_id);
-            if let Some(node) = self.nodes.get_mut(&target_id) {
-                node.needs_execution = true;
+        // Update input of all connected target nodes, the whole chain after them is stale now.
+        // A Loop node decides itself what to do with a result fed back into it
+        for (target_id, loop_back) in targets {
+            if !loop_back {
+                self.mark_dirty(target_id);
             }
+            self.update_node_input_from_all_sources(&target_id);
         }
     }
     
     fn update_node_input_from_all_sources(&mut self, target_node_id: &usize) {
-        // Find all source nodes connected to this target
-        let mut source_data: Vec<(f64, f64, String)> = self.connections
+        let inputs = self.port_outputs(*target_node_id, INPUT_PORT);
+        let system_inputs = self.port_outputs(*target_node_id, SYSTEM_PORT);
+    
+        // The target decides how the outputs connected to each of its ports are combined
+        let Some(target_node) = self.nodes.get_mut(target_node_id) else { return };
+        let input = target_node.node_type.combine_inputs(INPUT_PORT, inputs);
+        let input_changed = target_node.input != input;
+        target_node.input = input;
+        target_node.system_input = target_node.node_type.combine_inputs(SYSTEM_PORT, system_inputs);
+        
+        // Templates, routers and loops work on their inputs right away instead of waiting for a run
+        match &target_node.node_type {
+            NodeType::Template { template } => {
+                let template = template.clone();
+                let output = render_template(&template, &self.template_inputs(*target_node_id));
+                self.update_node_output(*target_node_id, output);
+            }
+            NodeType::Router {} => match target_node.input.clone() {
+                Some(input) => self.update_node_output(*target_node_id, input),
+                None => self.clear_node_output(*target_node_id),
+            },
+            NodeType::Loop { .. } if input_changed => self.restart_loop(*target_node_id),
+            NodeType::Review { .. } if input_changed => self.reopen_review(*target_node_id),
+            _ => {}
+        }
+    }
+    
+    fn clear_node_output(&mut self, node_id: usize) {
+        if let Some(node) = self.nodes.get_mut(&node_id) {
+            if node.output.take().is_some() {
+                self.propagate_output_to_connected_nodes(&node_id, OUTPUT_PORT);
+            }
+        }
+    }
+    
+    /// Whether a connection passes its source's output on, only the routes of a Router and the
+    /// connections of a rejected Review node are closed
+    pub fn is_route_taken(&self, conn: &Connection) -> bool {
+        let Some(source) = self.nodes.get(&conn.from_node_id) else { return false };
+        match source.node_type {
+            NodeType::Router {} => {}
+            NodeType::Review { status, .. } => return status != ReviewStatus::Rejected,
+            _ => return true,
+        }
+        let Some(input) = &source.output else { return false };
+        match &conn.route {
+            None => true,
+            Some(RouteRule::Otherwise) => !self.connections
+                .values()
+                .filter(|other| other.from_node_id == conn.from_node_id)
+                .any(|other| other.route.as_ref().is_some_and(|rule| rule.matches(input))),
+            Some(rule) => rule.matches(input),
+        }
+    }
+    
+    /// Whether a run passes over the node, which happens when every connection into it is a closed
+    /// route or comes from a node that is passed over itself
+    pub fn is_skipped(&self, node_id: usize) -> bool {
+        let mut incoming = self.connections
+            .values()
+            .filter(|conn| conn.to_node_id == node_id && !conn.is_loop_back())
+            .peekable();
+        incoming.peek().is_some()
+            && incoming.all(|conn| !self.is_route_taken(conn) || self.is_skipped(conn.from_node_id))
+    }
+    
+    /// A run passed over the node, its old output no longer reaches the nodes after it
+    pub fn skip_node(&mut self, node_id: usize) {
+        let Some(node) = self.nodes.get_mut(&node_id) else { return };
+        node.needs_execution = false;
+        node.skipped = true;
+        if node.output.take().is_some() {
+            self.propagate_output_to_connected_nodes(&node_id, OUTPUT_PORT);
+        }
+    }
+    
+    /// The nodes a Loop or Map node runs again for every value or item: the nodes after it
+    /// that feed back into it or lead to a node that does
+    pub fn body_nodes(&self, node_id: usize) -> HashSet<usize> {
+        let downstream = self.downstream_nodes(node_id);
+        let mut body = HashSet::new();
+        for conn in self.connections.values().filter(|conn| conn.to_node_id == node_id && conn.is_loop_back()) {
+            if downstream.contains(&conn.from_node_id) {
+                body.insert(conn.from_node_id);
+                body.extend(self.upstream_nodes(conn.from_node_id).intersection(&downstream));
+            }
+        }
+        body
+    }
+    
+    /// Nodes after a Loop node that aren't part of its body, they wait until the loop is done
+    pub fn nodes_after_loops(&self) -> HashSet<usize> {
+        let mut after = HashSet::new();
+        for (&loop_id, node) in &self.nodes {
+            if let NodeType::Loop { .. } = node.node_type {
+                let body = self.body_nodes(loop_id);
+                after.extend(self.downstream_nodes(loop_id).into_iter().filter(|node_id| !body.contains(node_id)));
+            }
+        }
+        after
+    }
+    
+    /// Loop nodes that have to start over because a node of their body changed since the last run
+    pub fn loops_to_restart(&self) -> Vec<usize> {
+        let mut loops: Vec<usize> = self.nodes
             .values()
-            .filter(|conn| &conn.to_node_id == target_node_id)
-            .filter_map(|conn| {
-                self.nodes.get(&conn.from_node_id)
-                    .and_then(|node| node.output.as_ref()
-                    .map(|output| 
-                        (node.position_y, node.position_x, output.clone())
-                    )
-                )
+            .filter(|node| match &node.node_type {
+                NodeType::Loop { iterations, .. } => !iterations.is_empty() || node.output != node.input,
+                _ => false,
             })
+            .map(|node| node.id)
+            .filter(|&loop_id| self.body_nodes(loop_id)
+                .iter()
+                .any(|node_id| self.nodes.get(node_id).is_some_and(|node| node.needs_execution)))
             .collect();
-        
-        // Sort by position: top-to-bottom (y), then left-to-right (x)
-        source_data.sort_by(|a, b| {
-            a.0.partial_cmp(&b.0)
-                .unwrap_or(std::cmp::Ordering::Equal)
-                .then_with(|| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
-        });
-        
-        // Concatenate all inputs
-        let combined_input = if source_data.is_empty() {
-            None
-        } else {
-            Some(source_data.into_iter().map(|(_, _, output)| output).collect::<Vec<_>>().join("\n\n"))
+        loops.sort_unstable();
+        loops
+    }
+    
+    /// Forget the values fed back into a Loop node and pass its input on again
+    pub fn restart_loop(&mut self, loop_id: usize) {
+        let Some(node) = self.nodes.get_mut(&loop_id) else { return };
+        if let NodeType::Loop { iterations, .. } = &mut node.node_type {
+            iterations.clear();
+        }
+        match node.input.clone() {
+            Some(input) => self.update_node_output(loop_id, input),
+            None => self.clear_node_output(loop_id),
+        }
+    }
+    
+    /// What a Loop node does with the value its body fed back
+    pub fn loop_step(&self, loop_id: usize) -> LoopStep {
+        let Some(node) = self.nodes.get(&loop_id) else { return LoopStep::Idle };
+        let NodeType::Loop { max_iterations, stop_pattern, stop_word, iterations } = &node.node_type else {
+            return LoopStep::Idle;
         };
+        let Some(feedback) = self.port_input(loop_id, FEEDBACK_PORT) else {
+            return LoopStep::Idle;
+        };
+        // The body didn't produce anything new, e.g. because it failed or gave the same answer again
+        if iterations.last() == Some(&feedback) {
+            return LoopStep::Idle;
+        }
+        let judge = self.port_input(loop_id, JUDGE_PORT);
+        if iterations.len() + 1 >= *max_iterations || loop_should_stop(stop_pattern, stop_word, &feedback, judge.as_deref()) {
+            LoopStep::Finish(feedback)
+        } else {
+            LoopStep::Continue(feedback)
+        }
+    }
+    
+    /// Record a value fed back into a Loop node, `feed_back` sends it through the body again
+    pub fn record_loop_iteration(&mut self, loop_id: usize, value: String, feed_back: bool) {
+        let Some(NodeType::Loop { iterations, .. }) = self.nodes.get_mut(&loop_id).map(|n| &mut n.node_type) else { return };
+        iterations.push(value.clone());
+        if feed_back {
+            self.update_node_output(loop_id, value);
+        }
+    }
     
-        // Update the target node's input
-        if let Some(target_node) = self.nodes.get_mut(target_node_id) {
-            target_node.input = combined_input;
+    /// A File Import node read its file, the nodes after it are stale when the content changed.
+    /// Returns whether it did
+    pub fn finish_import(&mut self, node_id: usize, file: ImportedFile) -> bool {
+        let Some(node) = self.nodes.get_mut(&node_id) else { return false };
+        node.error = None;
+        if let NodeType::FileImport { modified, format, .. } = &mut node.node_type {
+            *modified = Some(file.modified);
+            *format = Some(file.format);
         }
+        let changed = node.output.as_ref() != Some(&file.content);
+        self.update_node_output(node_id, file.content);
+        changed
+    }
+    
+    /// A Folder Import node read its folder, returns whether the output changed
+    pub fn finish_folder_import(&mut self, node_id: usize, listing: FolderListing) -> bool {
+        let Some(node) = self.nodes.get_mut(&node_id) else { return false };
+        node.error = None;
+        if let NodeType::FolderImport { files, left_out, .. } = &mut node.node_type {
+            *files = listing.files;
+            *left_out = listing.left_out;
+        }
+        let changed = node.output.as_ref() != Some(&listing.content);
+        self.update_node_output(node_id, listing.content);
+        changed
+    }
+    
+    /// A File Export node wrote its file, it is done until its input or settings change
+    pub fn finish_export(&mut self, node_id: usize, path: PathBuf) {
+        let Some(node) = self.nodes.get_mut(&node_id) else { return };
+        node.needs_execution = false;
+        node.error = None;
+        if let NodeType::FileExport { saved_path, .. } = &mut node.node_type {
+            *saved_path = Some(path);
+        }
+    }
+    
+    /// The input of a Review node changed, whatever was decided about the old one no longer holds
+    fn reopen_review(&mut self, node_id: usize) {
+        let Some(NodeType::Review { status, edited }) = self.nodes.get_mut(&node_id).map(|n| &mut n.node_type) else { return };
+        *status = ReviewStatus::Open;
+        *edited = None;
+        self.clear_node_output(node_id);
+    }
+    
+    pub fn edit_review(&mut self, node_id: usize, text: String) {
+        if let Some(NodeType::Review { edited, .. }) = self.nodes.get_mut(&node_id).map(|n| &mut n.node_type) {
+            *edited = Some(text);
+        }
+    }
+    
+    /// Record the decision about a Review node, an approved text goes on to the nodes after it
+    pub fn decide_review(&mut self, node_id: usize, decision: ReviewDecision) {
+        let Some(node) = self.nodes.get_mut(&node_id) else { return };
+        let NodeType::Review { status, .. } = &mut node.node_type else { return };
+        match decision {
+            ReviewDecision::Approve(text) => {
+                *status = ReviewStatus::Approved;
+                self.update_node_output(node_id, text);
+            }
+            ReviewDecision::Reject => {
+                *status = ReviewStatus::Rejected;
+                node.needs_execution = false;
+                // The closed connections take the output away from the nodes after it
+                node.output = None;
+                self.propagate_output_to_connected_nodes(&node_id, OUTPUT_PORT);
+            }
+        }
+    }
+    
+    /// Decide a Review node from the UI, a run waiting at it continues
+    pub fn answer_review(&mut self, node_id: usize, decision: ReviewDecision) {
+        if let Some(reply) = self.review_replies.remove(&node_id) {
+            // A run that is gone doesn't need the answer anymore
+            let _ = reply.send(decision.clone());
+            self.finish_node(node_id);
+        }
+        self.decide_review(node_id, decision);
+    }
+    
+    /// The bodies of the Map nodes that aren't working on an item, a run leaves them to the Map node
+    pub fn map_bodies(&self) -> HashSet<usize> {
+        self.nodes
+            .values()
+            .filter(|node| matches!(node.node_type, NodeType::Map { item: None, .. }))
+            .flat_map(|node| self.body_nodes(node.id))
+            .collect()
+    }
+    
+    /// Send an item through the body of a Map node, used on the copy of the workflow the item runs in.
+    /// Errors of earlier runs are cleared, the item fails only on errors of its own
+    pub fn set_map_item(&mut self, map_id: usize, value: String) {
+        for body_id in self.body_nodes(map_id) {
+            if let Some(node) = self.nodes.get_mut(&body_id) {
+                node.error = None;
+            }
+        }
+        let Some(NodeType::Map { item, .. }) = self.nodes.get_mut(&map_id).map(|n| &mut n.node_type) else { return };
+        *item = Some(value);
+        self.propagate_output_to_connected_nodes(&map_id, ITEM_PORT);
+    }
+    
+    pub fn start_map(&mut self, map_id: usize) {
+        if let Some(NodeType::Map { results, .. }) = self.nodes.get_mut(&map_id).map(|n| &mut n.node_type) {
+            results.clear();
+        }
+    }
+    
+    pub fn record_map_result(&mut self, map_id: usize, result: String) {
+        if let Some(NodeType::Map { results, .. }) = self.nodes.get_mut(&map_id).map(|n| &mut n.node_type) {
+            results.push(result);
+        }
+    }
+    
+    /// All items are done, the body ran for each of them so it is up to date as well
+    pub fn finish_map(&mut self, map_id: usize, output: String) {
+        self.update_node_output(map_id, output);
+        for node_id in self.body_nodes(map_id) {
+            if let Some(node) = self.nodes.get_mut(&node_id) {
+                node.needs_execution = false;
+            }
+        }
+    }
+    
+    /// The outgoing connections of a Router node with their rules, in the order they were made
+    pub fn router_outputs(&self, node_id: usize) -> Vec<RouterOutput> {
+        let mut connections: Vec<&Connection> = self.connections
+            .values()
+            .filter(|conn| conn.from_node_id == node_id)
+            .collect();
+        connections.sort_by_key(|conn| conn.id);
+        connections.into_iter()
+            .map(|conn| RouterOutput {
+                connection_id: conn.id,
+                target_title: self.nodes.get(&conn.to_node_id).map(|node| node.title.clone()).unwrap_or_default(),
+                rule: conn.route.clone(),
+                taken: self.is_route_taken(conn),
+            })
+            .collect()
+    }
+    
+    /// Change the rule of a Router's outgoing connection, only the branches whose route opened or
+    /// closed have to run again
+    pub fn set_route_rule(&mut self, conn_id: usize, rule: Option<RouteRule>) {
+        let Some(router_id) = self.connections.get(&conn_id).map(|conn| conn.from_node_id) else { return };
+        let taken = |workflow: &Workflow| -> Vec<(usize, bool)> {
+            workflow.connections
+                .values()
+                .filter(|conn| conn.from_node_id == router_id)
+                .map(|conn| (conn.to_node_id, workflow.is_route_taken(conn)))
+                .collect()
+        };
+        let before = taken(self);
+        if let Some(conn) = self.connections.get_mut(&conn_id) {
+            conn.route = rule;
+        }
+        for (target_id, is_taken) in taken(self) {
+            if !before.contains(&(target_id, is_taken)) {
+                self.mark_dirty(target_id);
+                self.update_node_input_from_all_sources(&target_id);
+            }
+        }
+    }
+    
+    /// The incoming connections of a Template node with their names and values, in connection order
+    pub fn template_inputs(&self, node_id: usize) -> Vec<TemplateInput> {
+        self.port_connections(node_id, INPUT_PORT)
+            .into_iter()
+            .map(|conn| {
+                let source = self.nodes.get(&conn.from_node_id);
+                TemplateInput {
+                    connection_id: conn.id,
+                    source_title: source.map(|node| node.title.clone()).unwrap_or_default(),
+                    name: conn.name.clone(),
+                    value: source.and_then(|node| node.port_output(&conn.from_port).cloned()),
+                }
+            })
+            .collect()
+    }
+    
+    pub fn update_template(&mut self, node_id: usize, new_template: String) {
+        let Some(NodeType::Template { template }) = self.nodes.get_mut(&node_id).map(|n| &mut n.node_type) else { return };
+        *template = new_template;
+        self.update_node_input_from_all_sources(&node_id);
+    }
+    
+    /// Name the input a connection provides to a Template node, an empty name removes it
+    pub fn rename_connection(&mut self, conn_id: usize, name: String) {
+        let Some(conn) = self.connections.get_mut(&conn_id) else { return };
+        let name = name.trim();
+        conn.name = (!name.is_empty()).then(|| name.to_string());
+        let target_node_id = conn.to_node_id;
+        self.update_node_input_from_all_sources(&target_node_id);
+    }
+    
+    /// Outputs of the nodes connected to a port, in connection order
+    fn port_outputs(&self, target_node_id: usize, port: &str) -> Vec<String> {
+        self.port_connections(target_node_id, port)
+            .into_iter()
+            .filter(|conn| self.is_route_taken(conn))
+            .filter_map(|conn| self.nodes.get(&conn.from_node_id)?.port_output(&conn.from_port).cloned())
+            .collect()
+    }
+    
+    /// The outputs connected to a port combined the way the node combines them
+    pub fn port_input(&self, node_id: usize, port: &str) -> Option<String> {
+        let node = self.nodes.get(&node_id)?;
+        node.node_type.combine_inputs(port, self.port_outputs(node_id, port))
     }
 
     pub fn complete_drawing_connection(&mut self) -> Option<Result<usize, String>> {
@@ -267,8 +743,11 @@ impl Workflow {
             }
         };
     
-        let result = if self.nodes.contains_key(&self.drawing_connection_state.source_node_id) {
-            Some(self.add_connection(self.drawing_connection_state.source_node_id, target_id))
+        let target_port = self.drawing_connection_state.target_port.clone().unwrap_or_else(|| INPUT_PORT.to_string());
+        let source_node_id = self.drawing_connection_state.source_node_id;
+        let source_port = self.drawing_connection_state.source_port.clone();
+        let result = if self.nodes.contains_key(&source_node_id) {
+            Some(self.add_connection(source_node_id, &source_port, target_id, &target_port))
         } else {
             Some(Err("Source node for connection no longer exists".to_string()))
         };
@@ -282,35 +761,228 @@ impl Workflow {
     }
     
     pub fn set_connection_target(&mut self, target_id: usize) {
-        println!("we are setting source node: {}, target id: {}", self.drawing_connection_state.source_node_id, target_id);
-        if self.drawing_connection_state.source_node_id != target_id  {
+        // Nodes without an input socket can't be the end of a connection
+        let accepts_input = self.nodes.get(&target_id).is_some_and(|node| !node.node_type.input_ports().is_empty());
+        if self.drawing_connection_state.source_node_id != target_id && accepts_input {
             self.drawing_connection_state.target_node_id = Some(target_id);
         }
     }
     
     pub fn clear_connection_target(&mut self) {
         self.drawing_connection_state.target_node_id = None;
+        self.drawing_connection_state.target_port = None;
+    }
+    
+    pub fn set_connection_target_port(&mut self, port: Option<String>) {
+        self.drawing_connection_state.target_port = port;
+    }
+    
+    pub fn is_running(&self) -> bool {
+        self.run_token.is_some()
+    }
+    
+    /// Stop the generation of a single node, a node stopped during a run also ends the run
+    pub fn stop_node(&mut self, node_id: usize) {
+        if let Some(token) = self.node_tokens.remove(&node_id) {
+            token.cancel();
+        }
+        self.review_replies.remove(&node_id);
+        if let Some(node) = self.nodes.get_mut(&node_id) {
+            node.is_executing = false;
+        }
     }
     
-    pub fn execution_order(&self) -> Vec<usize> {
-        // Get all model node IDs
-        let model_nodes: Vec<usize> = self.nodes
+    /// Stop the workflow run and every generation still in flight
+    pub fn stop_all(&mut self) {
+        if let Some(token) = self.run_token.take() {
+            token.cancel();
+        }
+        for (_, token) in self.node_tokens.drain() {
+            token.cancel();
+        }
+        self.review_replies.clear();
+        for node in self.nodes.values_mut() {
+            node.is_executing = false;
+        }
+    }
+    
+    pub fn model_messages_mut(&mut self, node_id: usize) -> Option<&mut Vec<ChatMessage>> {
+        match self.nodes.get_mut(&node_id).map(|n| &mut n.node_type) {
+            Some(NodeType::Model { messages, .. }) => Some(messages),
+            _ => None,
+        }
+    }
+    
+    /// Mirror the progress of a `WorkflowExecutor` running on a copy of this workflow
+    pub fn apply_event(&mut self, event: ExecutionEvent) {
+        match event {
+            ExecutionEvent::NodeStarted { node_id, cancel_token } => {
+                self.node_tokens.insert(node_id, cancel_token);
+                if let Some(node) = self.nodes.get_mut(&node_id) {
+                    node.is_executing = true;
+                    node.error = None;
+                    node.skipped = false;
+                }
+            }
+            ExecutionEvent::NodeSkipped { node_id } => {
+                self.skip_node(node_id);
+            }
+            ExecutionEvent::ResponseStarted { node_id } => {
+                if let Some(node) = self.nodes.get(&node_id) {
+                    self.outputs_before_response.insert(node_id, node.output.clone());
+                }
+                if let Some(messages) = self.model_messages_mut(node_id) {
+                    messages.push(ChatMessage {
+                        role: MessageRole::Assistant,
+                        content: String::new(),
+                        thinking: None,
+                    });
+                }
+            }
+            ExecutionEvent::ResponseChunk { node_id, chunk } => {
+                let Some(last_msg) = self.model_messages_mut(node_id).and_then(|m| m.last_mut()) else { return };
+                last_msg.append(&chunk);
+                let output = last_msg.content.clone();
+                // Only shown on the node, the nodes after it get the answer once it is complete
+                if let Some(node) = self.nodes.get_mut(&node_id) {
+                    node.output = Some(output);
+                }
+            }
+            ExecutionEvent::ResponseDiscarded { node_id } => {
+                self.discard_response(node_id);
+            }
+            ExecutionEvent::NodeFinished { node_id, output, .. } => {
+                if let Some(previous) = self.outputs_before_response.remove(&node_id) {
+                    if let Some(node) = self.nodes.get_mut(&node_id) {
+                        node.output = previous;
+                    }
+                }
+                self.update_node_output(node_id, output);
+                self.finish_node(node_id);
+            }
+            ExecutionEvent::NodeStopped { node_id } => {
+                self.discard_response(node_id);
+                if let Some(node) = self.nodes.get_mut(&node_id) {
+                    node.needs_execution = true;
+                }
+                self.finish_node(node_id);
+            }
+            ExecutionEvent::LoopRestarted { node_id } => {
+                self.restart_loop(node_id);
+            }
+            ExecutionEvent::LoopIteration { node_id, value, feed_back } => {
+                self.record_loop_iteration(node_id, value, feed_back);
+            }
+            ExecutionEvent::MapStarted { node_id, cancel_token } => {
+                self.node_tokens.insert(node_id, cancel_token);
+                if let Some(node) = self.nodes.get_mut(&node_id) {
+                    node.is_executing = true;
+                    node.error = None;
+                    node.skipped = false;
+                }
+                self.start_map(node_id);
+            }
+            ExecutionEvent::ReviewRequested { node_id, cancel_token, reply, .. } => {
+                self.node_tokens.insert(node_id, cancel_token);
+                self.review_replies.insert(node_id, reply);
+                if let Some(node) = self.nodes.get_mut(&node_id) {
+                    node.is_executing = true;
+                    node.error = None;
+                    node.skipped = false;
+                }
+            }
+            ExecutionEvent::FileRead { node_id, file } => {
+                self.finish_import(node_id, file);
+            }
+            ExecutionEvent::FolderRead { node_id, listing } => {
+                self.finish_folder_import(node_id, listing);
+            }
+            ExecutionEvent::FileWritten { node_id, path } => {
+                self.finish_export(node_id, path);
+            }
+            ExecutionEvent::MapItemFinished { node_id, output, .. } => {
+                self.record_map_result(node_id, output);
+            }
+            ExecutionEvent::MapFinished { node_id, output } => {
+                self.finish_map(node_id, output);
+                self.finish_node(node_id);
+            }
+            ExecutionEvent::NodeFailed { node_id, error } => {
+                if let Some(node) = self.nodes.get_mut(&node_id) {
+                    node.error = Some(error);
+                }
+                self.finish_node(node_id);
+            }
+        }
+    }
+    
+    /// Drop the partial answer of a response that didn't finish and put the previous output back
+    fn discard_response(&mut self, node_id: usize) {
+        let Some(previous) = self.outputs_before_response.remove(&node_id) else { return };
+        if let Some(messages) = self.model_messages_mut(node_id) {
+            messages.pop();
+        }
+        if let Some(node) = self.nodes.get_mut(&node_id) {
+            node.output = previous;
+        }
+    }
+    
+    fn finish_node(&mut self, node_id: usize) {
+        self.node_tokens.remove(&node_id);
+        self.review_replies.remove(&node_id);
+        if let Some(node) = self.nodes.get_mut(&node_id) {
+            node.is_executing = false;
+        }
+    }
+    
+    /// Nodes a scoped run may execute, `None` when the run isn't limited. The starting node is
+    /// marked dirty so it runs even when its output is up to date
+    pub fn prepare_scope(&mut self, scope: RunScope) -> Option<HashSet<usize>> {
+        let (node_id, mut nodes) = match scope {
+            RunScope::All => return None,
+            RunScope::Node(node_id) => (node_id, HashSet::new()),
+            RunScope::Downstream(node_id) => (node_id, self.downstream_nodes(node_id)),
+            RunScope::Upstream(node_id) => (node_id, self.upstream_nodes(node_id)),
+        };
+        self.mark_dirty(node_id);
+        nodes.insert(node_id);
+        Some(nodes)
+    }
+    
+    /// `execution_levels` restricted to a subgraph, the nodes outside keep their current output
+    pub fn subgraph_execution_levels(&self, nodes: &HashSet<usize>) -> Result<Vec<Vec<usize>>, Vec<usize>> {
+        let levels = self.execution_levels()?
+            .into_iter()
+            .map(|level| level.into_iter().filter(|node_id| nodes.contains(node_id)).collect::<Vec<_>>())
+            .filter(|level| !level.is_empty())
+            .collect();
+        Ok(levels)
+    }
+    
+    /// Model, Map, Review and File Export nodes that need execution grouped into levels, every node only depends on
+    /// nodes of earlier levels so the nodes within a level can run at the same time. The bodies
+    /// of Map nodes only run through their Map node. Nodes that depend on each other can't be
+    /// ordered, they are the error
+    pub fn execution_levels(&self) -> Result<Vec<Vec<usize>>, Vec<usize>> {
+        let map_bodies = self.map_bodies();
+        let runnable_nodes: Vec<usize> = self.nodes
             .iter()
             .filter_map(|(id, node)| match node.node_type {
-                NodeType::Model { .. } if node.needs_execution => Some(*id),
+                NodeType::Model { .. } | NodeType::Map { .. } | NodeType::Review { .. } | NodeType::FileExport { .. }
+                    if node.needs_execution && !map_bodies.contains(id) => Some(*id),
                 _ => None,
             })
             .collect();
         
-        // Build dependency map: node_id -> set of model nodes it depends on
-        let mut dependencies: HashMap<usize, HashSet<usize>> = 
-            model_nodes.iter().map(|&id| (id, HashSet::new())).collect();
-
-        for connection in self.connections.values() {
-            if model_nodes.contains(&connection.to_node_id) && model_nodes.contains(&connection.from_node_id) {
-                dependencies.get_mut(&connection.to_node_id).unwrap().insert(connection.from_node_id);
-            }
-        }
+        // Build dependency map: node_id -> set of runnable nodes it depends on, also
+        // through the other nodes between them
+        let dependencies: HashMap<usize, HashSet<usize>> = runnable_nodes
+            .iter()
+            .map(|&id| {
+                let upstream = self.upstream_nodes(id);
+                (id, runnable_nodes.iter().copied().filter(|other| upstream.contains(other)).collect())
+            })
+            .collect();
 
         // Topological sort
         let mut result = Vec::new();
@@ -318,19 +990,22 @@ impl Workflow {
 
         while !remaining.is_empty() {
             // Find nodes with no dependencies
-            let ready: Vec<usize> = remaining
+            let mut ready: Vec<usize> = remaining
                 .iter()
                 .filter(|(_, deps)| deps.is_empty())
                 .map(|(&id, _)| id)
                 .collect();
 
+            // Only a cycle is left, e.g. from a file edited by hand
             if ready.is_empty() {
-                break; // Circular dependency - return partial result
+                let mut cycle: Vec<usize> = remaining.into_keys().collect();
+                cycle.sort_unstable();
+                return Err(cycle);
             }
+            ready.sort_unstable();
 
             // Add ready nodes to result and remove from remaining
             for &node_id in &ready {
-                result.push(node_id);
                 remaining.remove(&node_id);
             }
 
@@ -340,8 +1015,50 @@ impl Workflow {
                     deps.remove(&completed);
                 }
             }
+            result.push(ready);
         }
-        result
+        Ok(result)
+    }
+}
+
+/// Run the nodes of `scope` that need it on a copy of the workflow, the run can be stopped
+/// through the workflow's run token
+pub fn start_workflow_run(mut workflow_state: Signal<Workflow>, scope: RunScope) {
+    if workflow_state.read().is_running() {
+        return;
+    }
+    let run_token = CancellationToken::new();
+    let nodes = {
+        let mut ws = workflow_state.write();
+        ws.run_token = Some(run_token.clone());
+        ws.prepare_scope(scope)
+    };
+    
+    let (events_tx, events_rx) = tokio::sync::mpsc::unbounded_channel();
+    let provider_config = file_manager::load_provider_config().unwrap_or_default();
+    let mut executor = WorkflowExecutor::new(workflow_state.read().clone(), events_tx)
+        .with_cancel_token(run_token.clone())
+        .with_provider_config(&provider_config);
+    if let Some(nodes) = nodes {
+        executor = executor.with_scope(nodes);
+    }
+    spawn(async move {
+        executor.run().await;
+    });
+    spawn(async move {
+        apply_events(workflow_state, events_rx).await;
+        
+        // A stopped run has already been cleared and may have been replaced by a new one
+        if !run_token.is_cancelled() {
+            workflow_state.write().run_token = None;
+        }
+    });
+}
+
+/// Apply executor events to the UI state until the executor is done
+pub async fn apply_events(mut workflow_state: Signal<Workflow>, mut events: UnboundedReceiver<ExecutionEvent>) {
+    while let Some(event) = events.recv().await {
+        workflow_state.write().apply_event(event);
     }
 }
 
@@ -381,4 +1098,140 @@ pub fn WorkflowManager(
             }
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::connections::COLLECT_PORT;
+
+    /// A workflow without the default nodes
+    fn empty_workflow() -> Workflow {
+        let mut workflow = Workflow::default();
+        workflow.nodes.clear();
+        workflow.connections.clear();
+        workflow
+    }
+
+    fn model(workflow: &mut Workflow) -> usize {
+        workflow.add_node(NodeType::model(ProviderType::Ollama), 0.0, 0.0)
+    }
+
+    #[test]
+    fn connections_that_would_loop_are_rejected() {
+        let mut workflow = empty_workflow();
+        let (first, second, third) = (model(&mut workflow), model(&mut workflow), model(&mut workflow));
+        workflow.add_connection(first, OUTPUT_PORT, second, INPUT_PORT).unwrap();
+        workflow.add_connection(second, OUTPUT_PORT, third, INPUT_PORT).unwrap();
+
+        assert!(workflow.add_connection(first, OUTPUT_PORT, first, INPUT_PORT).is_err());
+        assert!(workflow.add_connection(third, OUTPUT_PORT, first, INPUT_PORT).is_err());
+        // Not a loop, the first node feeds the third one twice
+        assert!(workflow.add_connection(first, OUTPUT_PORT, third, SYSTEM_PORT).is_ok());
+        assert_eq!(workflow.connections.len(), 3);
+    }
+
+    #[test]
+    fn duplicate_connections_and_unknown_ports_are_rejected() {
+        let mut workflow = empty_workflow();
+        let prompt = workflow.add_node(NodeType::Prompt {}, 0.0, 0.0);
+        let target = model(&mut workflow);
+        workflow.add_connection(prompt, OUTPUT_PORT, target, INPUT_PORT).unwrap();
+
+        assert!(workflow.add_connection(prompt, OUTPUT_PORT, target, INPUT_PORT).is_err());
+        assert!(workflow.add_connection(prompt, OUTPUT_PORT, target, FEEDBACK_PORT).is_err());
+        // Prompt nodes have no inputs at all
+        assert!(workflow.add_connection(target, OUTPUT_PORT, prompt, INPUT_PORT).is_err());
+        assert!(workflow.add_connection(prompt, OUTPUT_PORT, 99, INPUT_PORT).is_err());
+        assert_eq!(workflow.connections.len(), 1);
+    }
+
+    #[test]
+    fn loop_and_map_bodies_may_lead_back() {
+        let mut workflow = empty_workflow();
+        let loop_id = workflow.add_node(NodeType::Loop { max_iterations: 0, stop_pattern: String::new(), stop_word: String::new(), iterations: Vec::new() }, 0.0, 0.0);
+        let loop_body = model(&mut workflow);
+        workflow.add_connection(loop_id, OUTPUT_PORT, loop_body, INPUT_PORT).unwrap();
+        assert!(workflow.add_connection(loop_body, OUTPUT_PORT, loop_id, FEEDBACK_PORT).is_ok());
+        assert!(workflow.add_connection(loop_body, OUTPUT_PORT, loop_id, JUDGE_PORT).is_ok());
+        assert!(workflow.add_connection(loop_body, OUTPUT_PORT, loop_id, INPUT_PORT).is_err());
+
+        let map = workflow.add_node(NodeType::Map { split: Default::default(), delimiter: String::new(), max_parallel: 1, results: Vec::new(), item: None }, 0.0, 0.0);
+        let map_body = model(&mut workflow);
+        workflow.add_connection(map, ITEM_PORT, map_body, INPUT_PORT).unwrap();
+        assert!(workflow.add_connection(map_body, OUTPUT_PORT, map, COLLECT_PORT).is_ok());
+    }
+
+    #[test]
+    fn cycles_are_reported_instead_of_dropped() {
+        let mut workflow = empty_workflow();
+        let (first, second, third) = (model(&mut workflow), model(&mut workflow), model(&mut workflow));
+        workflow.add_connection(first, OUTPUT_PORT, second, INPUT_PORT).unwrap();
+        workflow.add_connection(second, OUTPUT_PORT, third, INPUT_PORT).unwrap();
+        assert_eq!(workflow.execution_levels(), Ok(vec![vec![first], vec![second], vec![third]]));
+
+        // add_connection refuses this, a workflow file edited by hand can still have it
+        let conn_id = workflow.next_connection_id;
+        workflow.connections.insert(conn_id, Connection {
+            id: conn_id,
+            from_node_id: third,
+            from_port: OUTPUT_PORT.to_string(),
+            to_node_id: second,
+            to_port: SYSTEM_PORT.to_string(),
+            order: 0,
+            name: None,
+            route: None,
+        });
+        assert_eq!(workflow.execution_levels(), Err(vec![second, third]));
+    }
+
+    #[test]
+    fn editing_a_prompt_only_dirties_the_nodes_after_it() {
+        let mut workflow = empty_workflow();
+        let edited = workflow.add_node(NodeType::Prompt {}, 0.0, 0.0);
+        let other = workflow.add_node(NodeType::Prompt {}, 0.0, 0.0);
+        let template = workflow.add_node(NodeType::Template { template: String::new() }, 0.0, 0.0);
+        let (after_template, after_both, after_other) = (model(&mut workflow), model(&mut workflow), model(&mut workflow));
+        workflow.add_connection(edited, OUTPUT_PORT, template, INPUT_PORT).unwrap();
+        workflow.add_connection(template, OUTPUT_PORT, after_template, INPUT_PORT).unwrap();
+        workflow.add_connection(after_template, OUTPUT_PORT, after_both, INPUT_PORT).unwrap();
+        workflow.add_connection(other, OUTPUT_PORT, after_both, INPUT_PORT).unwrap();
+        workflow.add_connection(other, OUTPUT_PORT, after_other, INPUT_PORT).unwrap();
+        for node in workflow.nodes.values_mut() {
+            node.needs_execution = false;
+        }
+
+        // Through the Template node, which isn't run itself
+        workflow.update_node_output(edited, "changed".to_string());
+        let dirty = |workflow: &Workflow, node_id: usize| workflow.nodes[&node_id].needs_execution;
+        assert!(dirty(&workflow, after_template));
+        assert!(dirty(&workflow, after_both));
+        assert!(!dirty(&workflow, after_other));
+        assert!(!dirty(&workflow, other));
+    }
+
+    #[test]
+    fn editing_an_input_of_a_map_body_dirties_the_map() {
+        let mut workflow = empty_workflow();
+        let items = workflow.add_node(NodeType::Prompt {}, 0.0, 0.0);
+        let instructions = workflow.add_node(NodeType::Prompt {}, 0.0, 0.0);
+        let map = workflow.add_node(NodeType::Map { split: Default::default(), delimiter: String::new(), max_parallel: 1, results: Vec::new(), item: None }, 0.0, 0.0);
+        let (body, after_map, unrelated) = (model(&mut workflow), model(&mut workflow), model(&mut workflow));
+        workflow.add_connection(items, OUTPUT_PORT, map, INPUT_PORT).unwrap();
+        workflow.add_connection(map, ITEM_PORT, body, INPUT_PORT).unwrap();
+        workflow.add_connection(instructions, OUTPUT_PORT, body, SYSTEM_PORT).unwrap();
+        workflow.add_connection(body, OUTPUT_PORT, map, COLLECT_PORT).unwrap();
+        workflow.add_connection(map, OUTPUT_PORT, after_map, INPUT_PORT).unwrap();
+        workflow.add_connection(items, OUTPUT_PORT, unrelated, INPUT_PORT).unwrap();
+        for node in workflow.nodes.values_mut() {
+            node.needs_execution = false;
+        }
+
+        // The instructions only reach the Map node through its body
+        workflow.update_node_output(instructions, "be brief".to_string());
+        let dirty = |workflow: &Workflow, node_id: usize| workflow.nodes[&node_id].needs_execution;
+        assert!(dirty(&workflow, body));
+        assert!(dirty(&workflow, map));
+        assert!(dirty(&workflow, after_map));
+        assert!(!dirty(&workflow, unrelated));
+    }
+}
@@ -0,0 +1,844 @@
+use dioxus::logger::tracing::warn;
+use futures::{future::join_all, stream, StreamExt};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::Arc;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tokio::sync::{mpsc::{self, UnboundedSender}, Semaphore, SemaphorePermit};
+use tokio_util::sync::CancellationToken;
+use crate::{
+    components::{ChatMessage, COLLECT_PORT, LoopStep, MessageRole, ModelOptions, Node, NodeError, NodeType, ProviderType, FolderListing, ImportedFile, ReviewDecision, Workflow, read_changed_import, read_folder_import, write_export},
+    llm::{self, ProviderConfig},
+    file_manager
+};
+
+/// Progress of a workflow run, applied to the UI state with `Workflow::apply_event`
+#[derive(Clone, Debug)]
+pub enum ExecutionEvent {
+    /// The node's generation was requested, cancelling the token stops only this node
+    NodeStarted { node_id: usize, cancel_token: CancellationToken },
+    /// No route of a Router leads to the node, so the run passes over it
+    NodeSkipped { node_id: usize },
+    /// The provider accepted the request and an empty assistant message was added
+    ResponseStarted { node_id: usize },
+    /// A streamed piece of the answer or of the model's thinking
+    ResponseChunk { node_id: usize, chunk: ChatMessage },
+    /// The partial answer of a failed generation was removed again
+    ResponseDiscarded { node_id: usize },
+    /// The answer is complete, `cached` when it was reused from an earlier run with the same prompt
+    NodeFinished { node_id: usize, output: String, cached: bool },
+    NodeStopped { node_id: usize },
+    NodeFailed { node_id: usize, error: NodeError },
+    /// The file of a File Import node changed since it was last read
+    FileRead { node_id: usize, file: ImportedFile },
+    /// A Folder Import node read its folder again
+    FolderRead { node_id: usize, listing: FolderListing },
+    /// A File Export node wrote its input to `path`
+    FileWritten { node_id: usize, path: PathBuf },
+    /// A node in the body of the Loop node changed, the loop starts over from its input
+    LoopRestarted { node_id: usize },
+    /// The body of the Loop node fed a value back, `feed_back` when it goes through the body again
+    LoopIteration { node_id: usize, value: String, feed_back: bool },
+    /// The Map node started on its items, cancelling the token stops all of them
+    MapStarted { node_id: usize, cancel_token: CancellationToken },
+    /// The result of the item at `index`, reported in item order
+    MapItemFinished { node_id: usize, index: usize, output: String },
+    /// All items are done, `output` is their results put together
+    MapFinished { node_id: usize, output: String },
+    /// The run waits at a Review node until a decision about `input` is sent through `reply`,
+    /// cancelling the token stops waiting
+    ReviewRequested {
+        node_id: usize,
+        input: String,
+        cancel_token: CancellationToken,
+        reply: UnboundedSender<ReviewDecision>,
+    },
+}
+
+/// How a single node execution ended
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NodeOutcome {
+    Finished,
+    /// The node's prompt couldn't be put together, it is marked as failed but does not end the run
+    PromptFailed,
+    Stopped,
+    Failed,
+}
+
+/// Everything needed to send a model node's prompt to its provider
+#[derive(Serialize)]
+struct Generation {
+    #[serde(skip)]
+    node_id: usize,
+    #[serde(skip)]
+    bypass_cache: bool,
+    provider: ProviderType,
+    /// Set for providers whose server can be changed, another server can give other answers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    server: Option<String>,
+    model_name: String,
+    thinking: bool,
+    options: ModelOptions,
+    messages: Vec<ChatMessage>,
+}
+
+impl Generation {
+    /// Hash of everything that decides the response, equal requests share a cache entry
+    fn cache_key(&self) -> String {
+        let request = serde_json::to_vec(self).unwrap_or_default();
+        format!("{:x}", Sha256::digest(&request))
+    }
+}
+
+enum GenerationResult {
+    Finished(ChatMessage),
+    Stopped,
+    Failed(NodeError),
+}
+
+/// Caps the number of generations in flight, across all providers and per provider
+struct ConcurrencyLimits {
+    global: Semaphore,
+    providers: Vec<(ProviderType, Semaphore)>,
+}
+
+impl ConcurrencyLimits {
+    fn new(config: &ProviderConfig) -> Self {
+        Self {
+            global: Semaphore::new(config.concurrency.max_parallel.max(1)),
+            providers: config.limited_providers()
+                .into_iter()
+                .map(|(provider, limit)| (provider, Semaphore::new(limit.max(1))))
+                .collect(),
+        }
+    }
+
+    /// Wait for a free slot, the provider slot comes first so a node queued behind its own
+    /// provider doesn't hold a global slot other providers could use
+    async fn acquire(&self, provider: &ProviderType) -> (Option<SemaphorePermit<'_>>, Option<SemaphorePermit<'_>>) {
+        let provider_permit = match self.providers.iter().find(|(limited, _)| limited == provider) {
+            Some((_, semaphore)) => semaphore.acquire().await.ok(),
+            None => None,
+        };
+        let global_permit = self.global.acquire().await.ok();
+        (provider_permit, global_permit)
+    }
+}
+
+/// Runs the model nodes of a workflow without any UI, working on its own copy of the workflow
+/// and reporting every change over the event channel
+pub struct WorkflowExecutor {
+    workflow: Workflow,
+    events: UnboundedSender<ExecutionEvent>,
+    cancel_token: CancellationToken,
+    /// Shared with the executors running the items of a Map node
+    limits: Arc<ConcurrencyLimits>,
+    scope: Option<HashSet<usize>>,
+}
+
+impl WorkflowExecutor {
+    pub fn new(workflow: Workflow, events: UnboundedSender<ExecutionEvent>) -> Self {
+        Self {
+            workflow,
+            events,
+            cancel_token: CancellationToken::new(),
+            limits: Arc::new(ConcurrencyLimits::new(&ProviderConfig::default())),
+            scope: None,
+        }
+    }
+
+    /// Cancelling the token stops the run and the generation in flight
+    pub fn with_cancel_token(mut self, cancel_token: CancellationToken) -> Self {
+        self.cancel_token = cancel_token;
+        self
+    }
+
+    /// Apply the concurrency limits from the provider settings
+    pub fn with_provider_config(mut self, config: &ProviderConfig) -> Self {
+        self.limits = Arc::new(ConcurrencyLimits::new(config));
+        self
+    }
+
+    /// Only run these nodes, the others keep their current output
+    pub fn with_scope(mut self, nodes: HashSet<usize>) -> Self {
+        self.scope = Some(nodes);
+        self
+    }
+
+    pub fn into_workflow(self) -> Workflow {
+        self.workflow
+    }
+
+    /// Execute every model node that needs it, the nodes of a level run at the same time.
+    /// A stopped or failed generation ends the run since the nodes after it would get stale input.
+    /// The body of a Loop node runs again for every value fed back, the nodes after the loop
+    /// wait until all loops are done
+    pub async fn run(&mut self) {
+        self.refresh_imports().await;
+        for loop_id in self.workflow.loops_to_restart() {
+            if self.in_scope(loop_id) {
+                self.workflow.restart_loop(loop_id);
+                self.emit(ExecutionEvent::LoopRestarted { node_id: loop_id });
+            }
+        }
+
+        let mut waiting = self.workflow.nodes_after_loops();
+        loop {
+            let levels = match &self.scope {
+                Some(nodes) => self.workflow.subgraph_execution_levels(nodes),
+                None => self.workflow.execution_levels(),
+            };
+            let levels = match levels {
+                Ok(levels) => levels,
+                Err(cycle) => {
+                    // Validation refuses such workflows, but a run must not pass over nodes silently
+                    let error = anyhow::anyhow!("Part of a cycle of connections, the nodes in it can't be ordered");
+                    for node_id in cycle {
+                        self.fail(node_id, &error);
+                    }
+                    return;
+                }
+            };
+            let levels = levels
+                .into_iter()
+                .map(|level| level.into_iter().filter(|node_id| !waiting.contains(node_id)).collect::<Vec<_>>())
+                .filter(|level| !level.is_empty())
+                .collect();
+            if !self.run_levels(levels).await {
+                return;
+            }
+            if self.advance_loops() {
+                continue;
+            }
+            if waiting.is_empty() {
+                return;
+            }
+            waiting.clear();
+        }
+    }
+
+    fn in_scope(&self, node_id: usize) -> bool {
+        self.scope.as_ref().is_none_or(|nodes| nodes.contains(&node_id))
+    }
+
+    /// Read the files of the File and Folder Import nodes the run depends on again, the nodes
+    /// after a changed file have to run
+    async fn refresh_imports(&mut self) {
+        let mut imports: Vec<Node> = self.workflow.nodes
+            .values()
+            .filter(|node| matches!(
+                node.node_type,
+                NodeType::FileImport { file_path: Some(_), .. } | NodeType::FolderImport { folder_path: Some(_), .. }
+            ))
+            .filter(|node| self.scope.as_ref().is_none_or(|nodes| {
+                nodes.contains(&node.id) || !self.workflow.downstream_nodes(node.id).is_disjoint(nodes)
+            }))
+            .cloned()
+            .collect();
+        imports.sort_unstable_by_key(|node| node.id);
+
+        // Extracting documents and walking folders blocks, the UI shares this thread
+        let reads = tokio::task::spawn_blocking(move || {
+            imports.iter().map(|node| (node.id, read_import(node))).collect::<Vec<_>>()
+        }).await.unwrap_or_default();
+
+        for (node_id, read) in reads {
+            match read {
+                Ok(Some(event)) => {
+                    self.workflow.apply_event(event.clone());
+                    self.emit(event);
+                }
+                Ok(None) => {}
+                // The nodes after it keep working with what was read before
+                Err(e) => self.fail(node_id, &e),
+            }
+        }
+    }
+
+    /// Record what the loop bodies fed back, returns whether any loop goes around again
+    fn advance_loops(&mut self) -> bool {
+        let mut loop_ids: Vec<usize> = self.workflow.nodes
+            .values()
+            .filter(|node| matches!(node.node_type, NodeType::Loop { .. }))
+            .map(|node| node.id)
+            .filter(|&node_id| self.in_scope(node_id))
+            .collect();
+        loop_ids.sort_unstable();
+
+        let mut continued = false;
+        for node_id in loop_ids {
+            let (value, feed_back) = match self.workflow.loop_step(node_id) {
+                LoopStep::Idle => continue,
+                LoopStep::Continue(value) => (value, true),
+                LoopStep::Finish(value) => (value, false),
+            };
+            self.workflow.record_loop_iteration(node_id, value.clone(), feed_back);
+            self.emit(ExecutionEvent::LoopIteration { node_id, value, feed_back });
+            continued |= feed_back;
+        }
+        continued
+    }
+
+    /// Execute the levels one after another, returns whether the run may go on
+    async fn run_levels(&mut self, levels: Vec<Vec<usize>>) -> bool {
+        for level in levels {
+            if self.cancel_token.is_cancelled() {
+                return false;
+            }
+
+            let mut generations = Vec::new();
+            let mut maps = Vec::new();
+            let mut reviews = Vec::new();
+            let mut exports = Vec::new();
+            for node_id in level {
+                // Nodes already generating, e.g. from the chat input, are left alone
+                if self.workflow.nodes.get(&node_id).is_none_or(|node| node.is_executing) {
+                    continue;
+                }
+                // Routers upstream were decided by the earlier levels
+                if self.workflow.is_skipped(node_id) {
+                    self.workflow.skip_node(node_id);
+                    self.emit(ExecutionEvent::NodeSkipped { node_id });
+                    continue;
+                }
+                match self.workflow.nodes.get(&node_id).map(|n| &n.node_type) {
+                    Some(NodeType::Map { .. }) => {
+                        maps.push(node_id);
+                        continue;
+                    }
+                    Some(NodeType::Review { .. }) => {
+                        reviews.push(node_id);
+                        continue;
+                    }
+                    Some(NodeType::FileExport { .. }) => {
+                        exports.push(node_id);
+                        continue;
+                    }
+                    _ => {}
+                }
+                match self.prepare_node(node_id) {
+                    Some(Ok(generation)) => generations.push(generation),
+                    Some(Err(e)) => self.fail(node_id, &e),
+                    None => {}
+                }
+            }
+
+            let results = join_all(generations.iter().map(|generation| self.generate(generation))).await;
+            let outcomes: Vec<NodeOutcome> = generations
+                .iter()
+                .zip(results)
+                .map(|(generation, result)| self.apply_result(generation.node_id, result))
+                .collect();
+            if outcomes.iter().any(|outcome| matches!(outcome, NodeOutcome::Stopped | NodeOutcome::Failed)) {
+                return false;
+            }
+            for map_id in maps {
+                if !self.run_map(map_id).await {
+                    return false;
+                }
+            }
+            for node_id in reviews {
+                if !self.review(node_id).await {
+                    return false;
+                }
+            }
+            // Nothing comes after a File Export node, a failed write doesn't hold up the others
+            for node_id in exports {
+                if self.workflow.nodes.get(&node_id).is_some_and(|node| node.input.is_none()) {
+                    self.workflow.skip_node(node_id);
+                    self.emit(ExecutionEvent::NodeSkipped { node_id });
+                    continue;
+                }
+                match self.workflow.nodes.get(&node_id).map(write_export) {
+                    Some(Ok(path)) => {
+                        self.workflow.finish_export(node_id, path.clone());
+                        self.emit(ExecutionEvent::FileWritten { node_id, path });
+                    }
+                    Some(Err(e)) => self.fail(node_id, &e),
+                    None => {}
+                }
+            }
+        }
+        !self.cancel_token.is_cancelled()
+    }
+
+    /// Wait for the decision about a Review node's input, returns whether the run may go on.
+    /// A rejection only closes the way to the nodes after the review
+    async fn review(&mut self, node_id: usize) -> bool {
+        let Some(input) = self.workflow.nodes.get(&node_id).and_then(|node| node.input.clone()) else {
+            // Nothing arrived to review, e.g. because the nodes before it were passed over
+            self.workflow.skip_node(node_id);
+            self.emit(ExecutionEvent::NodeSkipped { node_id });
+            return true;
+        };
+
+        let cancel_token = self.cancel_token.child_token();
+        let (reply, mut decisions) = mpsc::unbounded_channel();
+        self.emit(ExecutionEvent::ReviewRequested { node_id, input, cancel_token: cancel_token.clone(), reply });
+        let decision = tokio::select! {
+            decision = decisions.recv() => decision,
+            _ = cancel_token.cancelled() => None,
+        };
+        match decision {
+            Some(decision) => {
+                self.workflow.decide_review(node_id, decision);
+                true
+            }
+            // Stopped, or nobody is left to answer
+            None => {
+                self.emit(ExecutionEvent::NodeStopped { node_id });
+                false
+            }
+        }
+    }
+
+    /// Run the body of a Map node for each item of its input, every item on its own copy of the
+    /// workflow. Returns whether the run may go on
+    async fn run_map(&mut self, map_id: usize) -> bool {
+        let Some(node) = self.workflow.nodes.get(&map_id) else { return true };
+        let NodeType::Map { split, delimiter, max_parallel, .. } = &node.node_type else { return true };
+        let split = *split;
+        let max_parallel = *max_parallel;
+        let items = split.split(node.input.as_deref().unwrap_or_default(), delimiter);
+
+        let cancel_token = self.cancel_token.child_token();
+        self.emit(ExecutionEvent::MapStarted { node_id: map_id, cancel_token: cancel_token.clone() });
+        self.workflow.start_map(map_id);
+
+        let body = self.workflow.body_nodes(map_id);
+        let runs: Vec<_> = items
+            .into_iter()
+            .enumerate()
+            .map(|(index, item)| {
+                let mut workflow = self.workflow.clone();
+                workflow.set_map_item(map_id, item);
+                // The items would mix up each other's progress in the UI, only their results are reported
+                let (events, _) = tokio::sync::mpsc::unbounded_channel();
+                let executor = WorkflowExecutor {
+                    workflow,
+                    events,
+                    cancel_token: cancel_token.clone(),
+                    limits: self.limits.clone(),
+                    scope: Some(body.clone()),
+                };
+                run_map_item(executor, map_id, index, body.clone())
+            })
+            .collect();
+
+        let mut results = Vec::new();
+        let mut runs = stream::iter(runs).buffered(max_parallel.max(1)).enumerate();
+        while let Some((index, result)) = runs.next().await {
+            if cancel_token.is_cancelled() {
+                break;
+            }
+            match result {
+                // Items the body passed over, e.g. with a Router, have no result
+                Ok(None) => {}
+                Ok(Some(output)) => {
+                    self.emit(ExecutionEvent::MapItemFinished { node_id: map_id, index, output: output.clone() });
+                    self.workflow.record_map_result(map_id, output.clone());
+                    results.push(output);
+                }
+                Err(e) => {
+                    // The remaining items would only be thrown away
+                    cancel_token.cancel();
+                    self.fail(map_id, &e);
+                    return false;
+                }
+            }
+        }
+        if cancel_token.is_cancelled() {
+            self.emit(ExecutionEvent::NodeStopped { node_id: map_id });
+            return false;
+        }
+
+        let output = split.join(&results);
+        self.workflow.finish_map(map_id, output.clone());
+        self.emit(ExecutionEvent::MapFinished { node_id: map_id, output });
+        true
+    }
+
+    /// Execute the given nodes one after another regardless of whether they need it
+    pub async fn run_nodes(&mut self, node_ids: &[usize]) {
+        for &node_id in node_ids {
+            if self.cancel_token.is_cancelled() {
+                break;
+            }
+            if matches!(self.execute_node(node_id).await, Some(NodeOutcome::Stopped | NodeOutcome::Failed)) {
+                break;
+            }
+        }
+    }
+
+    /// Execute a single model node, returns `None` for nodes that have nothing to generate
+    pub async fn execute_node(&mut self, node_id: usize) -> Option<NodeOutcome> {
+        let generation = match self.prepare_node(node_id)? {
+            Ok(generation) => generation,
+            Err(e) => {
+                self.fail(node_id, &e);
+                return Some(NodeOutcome::PromptFailed);
+            }
+        };
+        let result = self.generate(&generation).await;
+        Some(self.apply_result(node_id, result))
+    }
+
+    /// Collect the prompt of a model node, `None` for other node types
+    fn prepare_node(&self, node_id: usize) -> Option<anyhow::Result<Generation>> {
+        let node = self.workflow.nodes.get(&node_id)?;
+        let NodeType::Model { provider, model_name, thinking, options, bypass_cache, .. } = &node.node_type else {
+            return None;
+        };
+        Some(node.prepare_prompt().map(|messages| Generation {
+            node_id,
+            bypass_cache: *bypass_cache,
+            provider: provider.clone(),
+            server: llm::configured_server(provider),
+            model_name: model_name.clone(),
+            thinking: *thinking,
+            options: options.clone(),
+            messages,
+        }))
+    }
+
+    /// Stream a generation, only reporting its progress so several can run side by side,
+    /// the result is recorded in the workflow afterwards by `apply_result`
+    async fn generate(&self, generation: &Generation) -> GenerationResult {
+        let node_id = generation.node_id;
+        let cache_key = generation.cache_key();
+        if !generation.bypass_cache {
+            if let Some(response) = file_manager::load_cached_response(&cache_key) {
+                return self.cached(node_id, response);
+            }
+        }
+
+        let _permits = tokio::select! {
+            permits = self.limits.acquire(&generation.provider) => permits,
+            _ = self.cancel_token.cancelled() => return GenerationResult::Stopped,
+        };
+
+        let cancel_token = self.cancel_token.child_token();
+        self.emit(ExecutionEvent::NodeStarted { node_id, cancel_token: cancel_token.clone() });
+
+        let request = llm::generate(
+            generation.provider.clone(),
+            Some(generation.model_name.clone()),
+            generation.messages.clone(),
+            Some(generation.thinking),
+            generation.options.clone()
+        );
+        let result = tokio::select! {
+            result = request => result,
+            _ = cancel_token.cancelled() => return self.stopped(node_id),
+        };
+        let mut receiver = match result {
+            Ok(receiver) => receiver,
+            Err(e) => return self.failed(node_id, &e),
+        };
+        self.emit(ExecutionEvent::ResponseStarted { node_id });
+
+        // Dropping the receiver on cancellation makes the provider abort the streaming response
+        let mut response = ChatMessage {
+            role: MessageRole::Assistant,
+            content: String::new(),
+            thinking: None,
+        };
+        loop {
+            let chunk = tokio::select! {
+                chunk = receiver.recv() => chunk,
+                _ = cancel_token.cancelled() => return self.stopped(node_id),
+            };
+            match chunk {
+                Some(Ok(chunk)) => {
+                    response.append(&chunk);
+                    self.emit(ExecutionEvent::ResponseChunk { node_id, chunk });
+                }
+                Some(Err(e)) => {
+                    // Drop the partial answer so a retry continues from the user's last message
+                    self.emit(ExecutionEvent::ResponseDiscarded { node_id });
+                    return self.failed(node_id, &e);
+                }
+                None => break,
+            }
+        }
+
+        // A bypassed node still refreshes the cache for the next run
+        if let Err(e) = file_manager::save_cached_response(&cache_key, &response) {
+            warn!("Failed to cache the response of node {}: {:#}", node_id, e);
+        }
+        self.emit(ExecutionEvent::NodeFinished { node_id, output: response.content.clone(), cached: false });
+        GenerationResult::Finished(response)
+    }
+
+    /// Report a cached response as if it was generated in one piece
+    fn cached(&self, node_id: usize, response: ChatMessage) -> GenerationResult {
+        self.emit(ExecutionEvent::NodeStarted { node_id, cancel_token: self.cancel_token.child_token() });
+        self.emit(ExecutionEvent::ResponseStarted { node_id });
+        self.emit(ExecutionEvent::ResponseChunk { node_id, chunk: response.clone() });
+        self.emit(ExecutionEvent::NodeFinished { node_id, output: response.content.clone(), cached: true });
+        GenerationResult::Finished(response)
+    }
+
+    /// Record the result of a generation in the executor's own copy of the workflow
+    fn apply_result(&mut self, node_id: usize, result: GenerationResult) -> NodeOutcome {
+        match result {
+            GenerationResult::Finished(response) => {
+                let output = response.content.clone();
+                if let Some(messages) = self.workflow.model_messages_mut(node_id) {
+                    messages.push(response);
+                }
+                if let Some(node) = self.workflow.nodes.get_mut(&node_id) {
+                    node.error = None;
+                }
+                self.workflow.update_node_output(node_id, output);
+                NodeOutcome::Finished
+            }
+            GenerationResult::Stopped => NodeOutcome::Stopped,
+            GenerationResult::Failed(error) => {
+                if let Some(node) = self.workflow.nodes.get_mut(&node_id) {
+                    node.error = Some(error);
+                }
+                NodeOutcome::Failed
+            }
+        }
+    }
+
+    fn emit(&self, event: ExecutionEvent) {
+        // Nobody listening is fine, e.g. when the window was closed during a run
+        let _ = self.events.send(event);
+    }
+
+    fn stopped(&self, node_id: usize) -> GenerationResult {
+        self.emit(ExecutionEvent::NodeStopped { node_id });
+        GenerationResult::Stopped
+    }
+
+    fn failed(&self, node_id: usize, error: &anyhow::Error) -> GenerationResult {
+        let error = NodeError::new(error);
+        self.emit(ExecutionEvent::NodeFailed { node_id, error: error.clone() });
+        GenerationResult::Failed(error)
+    }
+
+    /// Mark a node whose prompt couldn't be prepared
+    fn fail(&mut self, node_id: usize, error: &anyhow::Error) {
+        let result = self.failed(node_id, error);
+        self.apply_result(node_id, result);
+    }
+}
+
+/// The event reporting what an import node read, `None` when its file is unchanged. A folder
+/// has no single modification time, so it is always read
+fn read_import(node: &Node) -> anyhow::Result<Option<ExecutionEvent>> {
+    match node.node_type {
+        NodeType::FolderImport { .. } => read_folder_import(node)
+            .map(|listing| Some(ExecutionEvent::FolderRead { node_id: node.id, listing })),
+        _ => read_changed_import(node)
+            .map(|file| file.map(|file| ExecutionEvent::FileRead { node_id: node.id, file })),
+    }
+}
+
+/// Run the body of a Map node for one item, returns what came back into the collect port
+async fn run_map_item(mut executor: WorkflowExecutor, map_id: usize, index: usize, body: HashSet<usize>) -> anyhow::Result<Option<String>> {
+    // Boxed since the body can hold Map nodes of its own
+    Box::pin(executor.run()).await;
+    let workflow = executor.into_workflow();
+    let failed = workflow.nodes
+        .values()
+        .filter(|node| body.contains(&node.id))
+        .find_map(|node| Some((node, node.error.as_ref()?)));
+    if let Some((node, error)) = failed {
+        anyhow::bail!("Item {} failed in {}: {}", index + 1, node.title, error.message);
+    }
+    Ok(workflow.port_input(map_id, COLLECT_PORT))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::{COLLECT_PORT, FEEDBACK_PORT, INPUT_PORT, ITEM_PORT, OUTPUT_PORT, RouteRule, SplitMode};
+    use crate::llm::test_server::{serve_openai, Response};
+
+    /// A workflow without the default nodes
+    fn empty_workflow() -> Workflow {
+        let mut workflow = Workflow::default();
+        workflow.nodes.clear();
+        workflow.connections.clear();
+        workflow
+    }
+
+    fn prompt(workflow: &mut Workflow, text: &str) -> usize {
+        let node_id = workflow.add_node(NodeType::Prompt {}, 0.0, 0.0);
+        workflow.update_node_output(node_id, text.to_string());
+        node_id
+    }
+
+    /// An OpenAI model node that always asks the server, the cache could hold answers of other runs
+    fn model(workflow: &mut Workflow) -> usize {
+        let node_id = workflow.add_node(NodeType::model(ProviderType::OpenAI), 0.0, 0.0);
+        if let NodeType::Model { bypass_cache, .. } = &mut workflow.nodes.get_mut(&node_id).unwrap().node_type {
+            *bypass_cache = true;
+        }
+        node_id
+    }
+
+    fn connect(workflow: &mut Workflow, from: usize, from_port: &str, to: usize, to_port: &str) -> usize {
+        workflow.add_connection(from, from_port, to, to_port).unwrap()
+    }
+
+    /// The first user message of a request, which holds the node's input
+    fn prompt_of(request: &serde_json::Value) -> String {
+        request["messages"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|message| message["role"] == "user")
+            .and_then(|message| message["content"].as_str())
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    /// The events that matter for the order of a run, streamed chunks left out
+    fn describe(event: &ExecutionEvent) -> Option<String> {
+        Some(match event {
+            ExecutionEvent::NodeStarted { node_id, .. } => format!("started {}", node_id),
+            ExecutionEvent::NodeSkipped { node_id } => format!("skipped {}", node_id),
+            ExecutionEvent::NodeFinished { node_id, output, .. } => format!("finished {}: {}", node_id, output),
+            ExecutionEvent::NodeStopped { node_id } => format!("stopped {}", node_id),
+            ExecutionEvent::NodeFailed { node_id, error } => format!("failed {}: {}", node_id, error.message),
+            ExecutionEvent::LoopIteration { node_id, value, feed_back } => format!("loop {}: {} again {}", node_id, value, feed_back),
+            ExecutionEvent::MapStarted { node_id, .. } => format!("map started {}", node_id),
+            ExecutionEvent::MapItemFinished { node_id, index, output } => format!("map item {} {}: {}", node_id, index, output),
+            ExecutionEvent::MapFinished { node_id, output } => format!("map finished {}: {}", node_id, output),
+            _ => return None,
+        })
+    }
+
+    /// Run the workflow against a server answering every prompt with `answer`
+    async fn run(workflow: Workflow, answer: fn(&str) -> String) -> (Vec<String>, Workflow) {
+        let _server = serve_openai(move |request| Response::stream(&[&answer(&prompt_of(request))])).await;
+        // Responses are cached even when the cache is bypassed
+        std::env::set_var("XDG_DATA_HOME", std::env::temp_dir().join("mosaik-tests"));
+
+        let (events_tx, mut events_rx) = mpsc::unbounded_channel();
+        let mut executor = WorkflowExecutor::new(workflow, events_tx);
+        executor.run().await;
+        let workflow = executor.into_workflow();
+
+        let mut events = Vec::new();
+        while let Ok(event) = events_rx.try_recv() {
+            events.extend(describe(&event));
+        }
+        (events, workflow)
+    }
+
+    fn position(events: &[String], event: &str) -> usize {
+        events.iter().position(|e| e == event).unwrap_or_else(|| panic!("no \"{}\" in {:?}", event, events))
+    }
+
+    #[test]
+    fn cache_keys_differ_between_servers() {
+        let generation = |server: &str| Generation {
+            node_id: 0,
+            bypass_cache: false,
+            provider: ProviderType::Ollama,
+            server: Some(server.to_string()),
+            model_name: "llama3".to_string(),
+            thinking: false,
+            options: ModelOptions::default(),
+            messages: Vec::new(),
+        };
+        assert_eq!(generation("http://localhost:11434").cache_key(), generation("http://localhost:11434").cache_key());
+        assert_ne!(generation("http://localhost:11434").cache_key(), generation("http://gpu-box:11434").cache_key());
+    }
+
+    #[tokio::test]
+    async fn levels_run_after_the_nodes_they_depend_on() {
+        let mut workflow = empty_workflow();
+        let input = prompt(&mut workflow, "hi");
+        let (first, second, last) = (model(&mut workflow), model(&mut workflow), model(&mut workflow));
+        connect(&mut workflow, input, OUTPUT_PORT, first, INPUT_PORT);
+        connect(&mut workflow, input, OUTPUT_PORT, second, INPUT_PORT);
+        connect(&mut workflow, first, OUTPUT_PORT, last, INPUT_PORT);
+        connect(&mut workflow, second, OUTPUT_PORT, last, INPUT_PORT);
+
+        let (events, workflow) = run(workflow, |prompt| format!("{}!", prompt)).await;
+        assert_eq!(events.len(), 6, "{:?}", events);
+        let started_last = position(&events, &format!("started {}", last));
+        assert!(position(&events, &format!("finished {}: hi!", first)) < started_last, "{:?}", events);
+        assert!(position(&events, &format!("finished {}: hi!", second)) < started_last, "{:?}", events);
+        assert_eq!(events[5], format!("finished {}: hi!\n\nhi!!", last));
+        assert!(workflow.nodes.values().all(|node| !node.needs_execution));
+    }
+
+    #[tokio::test]
+    async fn branches_a_router_does_not_take_are_skipped() {
+        let mut workflow = empty_workflow();
+        let input = prompt(&mut workflow, "a billing question");
+        let router = workflow.add_node(NodeType::Router {}, 0.0, 0.0);
+        let (billing, other, after_other) = (model(&mut workflow), model(&mut workflow), model(&mut workflow));
+        connect(&mut workflow, input, OUTPUT_PORT, router, INPUT_PORT);
+        let billing_route = connect(&mut workflow, router, OUTPUT_PORT, billing, INPUT_PORT);
+        workflow.set_route_rule(billing_route, Some(RouteRule::Contains { text: "billing".to_string() }));
+        let other_route = connect(&mut workflow, router, OUTPUT_PORT, other, INPUT_PORT);
+        workflow.set_route_rule(other_route, Some(RouteRule::Otherwise));
+        connect(&mut workflow, other, OUTPUT_PORT, after_other, INPUT_PORT);
+
+        let (events, workflow) = run(workflow, |prompt| prompt.to_uppercase()).await;
+        assert_eq!(events, [
+            format!("skipped {}", other),
+            format!("started {}", billing),
+            format!("finished {}: A BILLING QUESTION", billing),
+            format!("skipped {}", after_other),
+        ]);
+        assert!(workflow.nodes[&after_other].skipped);
+    }
+
+    #[tokio::test]
+    async fn loops_feed_back_until_the_stop_pattern_matches() {
+        let mut workflow = empty_workflow();
+        let input = prompt(&mut workflow, "a");
+        let loop_id = workflow.add_node(NodeType::Loop { max_iterations: 0, stop_pattern: String::new(), stop_word: String::new(), iterations: Vec::new() }, 0.0, 0.0);
+        if let NodeType::Loop { stop_pattern, .. } = &mut workflow.nodes.get_mut(&loop_id).unwrap().node_type {
+            *stop_pattern = "^a{3}$".to_string();
+        }
+        let body = model(&mut workflow);
+        connect(&mut workflow, input, OUTPUT_PORT, loop_id, INPUT_PORT);
+        connect(&mut workflow, loop_id, OUTPUT_PORT, body, INPUT_PORT);
+        connect(&mut workflow, body, OUTPUT_PORT, loop_id, FEEDBACK_PORT);
+
+        let (events, workflow) = run(workflow, |prompt| format!("{}a", prompt)).await;
+        assert_eq!(events, [
+            format!("started {}", body),
+            format!("finished {}: aa", body),
+            format!("loop {}: aa again true", loop_id),
+            format!("started {}", body),
+            format!("finished {}: aaa", body),
+            format!("loop {}: aaa again false", loop_id),
+        ]);
+        let NodeType::Loop { iterations, .. } = &workflow.nodes[&loop_id].node_type else { unreachable!() };
+        assert_eq!(iterations, &["aa", "aaa"]);
+    }
+
+    #[tokio::test]
+    async fn maps_collect_the_result_of_every_item() {
+        let mut workflow = empty_workflow();
+        let input = prompt(&mut workflow, "x\ny\nz");
+        let map = workflow.add_node(NodeType::Map { split: SplitMode::Lines, delimiter: String::new(), max_parallel: 2, results: Vec::new(), item: None }, 0.0, 0.0);
+        let body = model(&mut workflow);
+        connect(&mut workflow, input, OUTPUT_PORT, map, INPUT_PORT);
+        connect(&mut workflow, map, ITEM_PORT, body, INPUT_PORT);
+        connect(&mut workflow, body, OUTPUT_PORT, map, COLLECT_PORT);
+
+        let (events, workflow) = run(workflow, |prompt| prompt.to_uppercase()).await;
+        assert_eq!(events, [
+            format!("map started {}", map),
+            format!("map item {} 0: X", map),
+            format!("map item {} 1: Y", map),
+            format!("map item {} 2: Z", map),
+            format!("map finished {}: X\n\nY\n\nZ", map),
+        ]);
+        assert_eq!(workflow.nodes[&map].output.as_deref(), Some("X\n\nY\n\nZ"));
+        assert!(!workflow.nodes[&body].needs_execution);
+    }
+}
@@ -1,9 +1,12 @@
-use anyhow::Result;
-use std::path::PathBuf;
+use anyhow::{Result, Context};
+use std::path::{Path, PathBuf};
 use std::fs;
-use crate::components::Workflow;
+use std::io::Write;
+use crate::components::{ChatMessage, Workflow};
+use crate::llm::ProviderConfig;
 
 const WORKFLOW_FILENAME: &str = "default.json";
+const PROVIDER_CONFIG_FILENAME: &str = "providers.json";
 
 /// Get the application directory, creating it if it doesn't exist
 fn get_app_directory() -> Result<PathBuf> {
@@ -34,6 +37,18 @@ fn get_workflows_directory() -> Result<PathBuf> {
     Ok(workflows_dir)
 }
 
+/// Get the directory of cached model responses, creating it if it doesn't exist
+fn get_cache_directory() -> Result<PathBuf> {
+    let cache_dir = get_app_directory()?.join("cache");
+    
+    // Create the directory if it doesn't exist
+    if !cache_dir.exists() {
+        fs::create_dir_all(&cache_dir)?;
+    }
+    
+    Ok(cache_dir)
+}
+
 /// Save the workflow to the default file
 pub fn save_default_workflow(workflow: &Workflow) -> Result<()> {
     let workflow_dir = get_workflows_directory()?;
@@ -48,10 +63,95 @@ pub fn save_default_workflow(workflow: &Workflow) -> Result<()> {
 /// Load the workflow from the default file
 pub fn load_default_workflow() -> Result<Workflow> {
     let workflow_dir = get_workflows_directory()?;
-    let file_path = workflow_dir.join(WORKFLOW_FILENAME);
+    load_workflow(&workflow_dir.join(WORKFLOW_FILENAME))
+}
+
+/// Load a workflow from any file saved by `save_default_workflow`
+pub fn load_workflow(file_path: &Path) -> Result<Workflow> {
+    let json_content = fs::read_to_string(file_path)
+        .with_context(|| format!("Failed to read workflow {}", file_path.display()))?;
+    let workflow: Workflow = serde_json::from_str(&json_content)
+        .with_context(|| format!("Failed to parse workflow {}", file_path.display()))?;
+    
+    Ok(workflow)
+}
+
+/// Write the content of a File Export node to `<folder>/<file_name>.<file_type>`, `append` adds
+/// it to the end of an existing file after a blank line
+pub fn export_file(folder: &str, file_name: &str, file_type: &str, content: &str, append: bool) -> Result<PathBuf> {
+    let file_path = Path::new(folder).join(format!("{}.{}", file_name, file_type));
+    let existing = if append { fs::metadata(&file_path).map_or(0, |metadata| metadata.len()) } else { 0 };
+    let result = if existing > 0 {
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&file_path)
+            .and_then(|mut file| write!(file, "\n\n{}", content))
+    } else {
+        fs::write(&file_path, content)
+    };
+    result.with_context(|| format!("Failed to save file {}", file_path.display()))?;
+    
+    Ok(file_path)
+}
+
+/// Save the provider configuration to the app directory
+pub fn save_provider_config(config: &ProviderConfig) -> Result<()> {
+    let file_path = get_app_directory()?.join(PROVIDER_CONFIG_FILENAME);
+    
+    let json_content = serde_json::to_string_pretty(config)?;
+    fs::write(file_path, json_content)?;
+    
+    Ok(())
+}
+
+/// Load the provider configuration from the app directory
+pub fn load_provider_config() -> Result<ProviderConfig> {
+    let file_path = get_app_directory()?.join(PROVIDER_CONFIG_FILENAME);
     
     let json_content = fs::read_to_string(file_path)?;
-    let workflow: Workflow = serde_json::from_str(&json_content)?;
+    let config: ProviderConfig = serde_json::from_str(&json_content)?;
     
-    Ok(workflow)
+    Ok(config)
+}
+
+/// Load the cached response stored under `key`, `None` when there is none or it can't be read
+pub fn load_cached_response(key: &str) -> Option<ChatMessage> {
+    let file_path = get_cache_directory().ok()?.join(format!("{}.json", key));
+    let json_content = fs::read_to_string(file_path).ok()?;
+    serde_json::from_str(&json_content).ok()
+}
+
+/// Store a model response under `key`, replacing an earlier one
+pub fn save_cached_response(key: &str, response: &ChatMessage) -> Result<()> {
+    let file_path = get_cache_directory()?.join(format!("{}.json", key));
+    
+    let json_content = serde_json::to_string(response)?;
+    fs::write(&file_path, json_content)
+        .with_context(|| format!("Failed to write cache entry {}", file_path.display()))?;
+    
+    Ok(())
+}
+
+/// Number of cached responses and their total size in bytes
+pub fn response_cache_size() -> Result<(usize, u64)> {
+    let mut entries = 0;
+    let mut bytes = 0;
+    for entry in fs::read_dir(get_cache_directory()?)? {
+        let metadata = entry?.metadata()?;
+        if metadata.is_file() {
+            entries += 1;
+            bytes += metadata.len();
+        }
+    }
+    
+    Ok((entries, bytes))
+}
+
+/// Remove every cached response
+pub fn clear_response_cache() -> Result<()> {
+    let cache_dir = get_cache_directory()?;
+    fs::remove_dir_all(&cache_dir)
+        .with_context(|| format!("Failed to remove {}", cache_dir.display()))?;
+    
+    Ok(())
 }
\ No newline at end of file
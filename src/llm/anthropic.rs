@@ -1,10 +1,29 @@
-use dioxus::logger::tracing::{info, error};
+use dioxus::logger::tracing::{info, error, warn};
 use anyhow::{Result, Context};
 use crate::{
-    llm::ApiManager, 
-    components::{ChatMessage, MessageRole}, llm::ThinkingConfig
+    llm::{ApiManager, ApiError}, 
+    components::{ChatMessage, MessageRole, ModelOptions}, llm::ThinkingConfig
 };
-use super::{LLMProvider, LLMRequest, LLMResponse};
+use super::{LLMProvider, LLMRequest, LLMResponse, next_chunk};
+
+/// Tokens extended thinking may use before the answer starts
+const THINKING_BUDGET_TOKENS: u32 = 2000;
+
+/// The known models and the longest answer each may give, used when a node doesn't set max_tokens
+const MODELS: [(&str, u32); 3] = [
+    ("claude-sonnet-4-20250514", 32000),
+    ("claude-opus-4-20250514", 32000),
+    ("claude-3-5-haiku-20241022", 8192),
+];
+
+/// Every model allows at least this many, for models not in the list
+const FALLBACK_MAX_TOKENS: u32 = 8192;
+
+fn default_max_tokens(model: &str) -> u32 {
+    MODELS.iter()
+        .find(|(name, _)| *name == model)
+        .map_or(FALLBACK_MAX_TOKENS, |(_, max_tokens)| *max_tokens)
+}
 
 pub struct AnthropicClient {
     client: reqwest::Client,
@@ -15,11 +34,11 @@ pub struct AnthropicClient {
 
 impl LLMProvider for AnthropicClient {
     fn new() -> Self {
-        let api_key = if let Ok(key_manager) = ApiManager::new() {
-            key_manager.get_anthropic_key().ok()
-        } else {
-            None
-        };
+        // The environment is the fallback for machines without a keyring, e.g. when run from CI
+        let api_key = ApiManager::new()
+            .ok()
+            .and_then(|key_manager| key_manager.get_anthropic_key().ok())
+            .or_else(|| std::env::var("ANTHROPIC_API_KEY").ok());
         Self {
             client: reqwest::Client::new(),
             base_url: "https://api.anthropic.com/v1".to_string(),
@@ -28,7 +47,7 @@ impl LLMProvider for AnthropicClient {
         }
     }
 
-    async fn generate(&self, model: Option<String>, messages: Vec<ChatMessage>, thinking: Option<bool>) -> Result<tokio::sync::mpsc::Receiver<ChatMessage>> {
+    async fn generate(&self, model: Option<String>, messages: Vec<ChatMessage>, thinking: Option<bool>, mut options: ModelOptions) -> Result<tokio::sync::mpsc::Receiver<Result<ChatMessage>>> {
         let model = model.unwrap_or_else(|| self.default_model.clone());
         let url = format!("{}/messages", self.base_url);
         
@@ -36,30 +55,53 @@ impl LLMProvider for AnthropicClient {
             Some(true) => {
                 Some(ThinkingConfig {
                     thinking_type: "enabled".to_string(),
-                    budget_tokens: 2000
+                    budget_tokens: THINKING_BUDGET_TOKENS
                 })
             }
             _ => None
         };
         
-        let max_tokens = if model == "claude-3-5-haiku-20241022" {
-            8192
-        } else {
-            32000
-        };
+        // Anthropic takes the system prompt as a top-level field rather than a message
+        let (system_messages, messages): (Vec<ChatMessage>, Vec<ChatMessage>) = messages
+            .into_iter()
+            .partition(|message| message.role == MessageRole::System);
+        let system = system_messages
+            .into_iter()
+            .map(|message| message.content)
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        
+        let mut max_tokens = options.max_tokens.unwrap_or_else(|| default_max_tokens(&model));
+        // The API refuses thinking with sampling options and counts the thinking into max_tokens
+        if thinking_config.is_some() {
+            if max_tokens <= THINKING_BUDGET_TOKENS {
+                max_tokens += THINKING_BUDGET_TOKENS;
+            }
+            if options.temperature.is_some() || options.top_p.is_some() || options.top_k.is_some() {
+                warn!("Ignoring temperature, top_p and top_k, Anthropic doesn't allow them with thinking");
+                options.temperature = None;
+                options.top_p = None;
+                options.top_k = None;
+            }
+        }
         let request = LLMRequest {
             model,
             messages,
+            system: (!system.is_empty()).then_some(system),
             stream: true,
             max_tokens: Some(max_tokens),
-            think: None,
-            thinking: thinking_config
+            temperature: options.temperature,
+            top_p: options.top_p,
+            top_k: options.top_k,
+            stop_sequences: (!options.stop.is_empty()).then_some(options.stop),
+            thinking: thinking_config,
+            ..Default::default()
         };
 
         info!("Sending request to Anthropic API");
         
         let api_key = self.api_key.as_ref()
-            .context("Anthropic API key not configured")?;
+            .ok_or_else(|| ApiError::missing_key("Anthropic"))?;
         let mut response = self.client
             .post(&url)
             .header("x-api-key", api_key)
@@ -74,14 +116,14 @@ impl LLMProvider for AnthropicClient {
             let error_text = response.text().await
                 .unwrap_or_else(|_| "Unable to read error response".to_string());
             error!("Anthropic API returned error: Status {}, Content: {}", status, error_text);
-            anyhow::bail!("Anthropic API error: Status {}: {}", status, error_text);
+            return Err(ApiError::from_response("Anthropic", status, &error_text).into());
         }
 
-        let (tx, rx) = tokio::sync::mpsc::channel::<ChatMessage>(100);
+        let (tx, rx) = tokio::sync::mpsc::channel::<Result<ChatMessage>>(100);
         tokio::spawn(async move {
             let mut buffer = String::new();
             
-            while let Ok(Some(bytes)) = response.chunk().await {
+            while let Some(bytes) = next_chunk(&mut response, &tx).await {
                 buffer.push_str(&String::from_utf8_lossy(&bytes));
                 
                 while let Some(line_end) = buffer.find('\n') {
@@ -97,6 +139,14 @@ impl LLMProvider for AnthropicClient {
                         continue;
                     };
                     
+                    if event.event_type == "error" {
+                        if let Some(stream_error) = event.error {
+                            error!("Anthropic stream returned error: {:?}", stream_error);
+                            let api_error = ApiError::new("Anthropic", stream_error.kind(), stream_error.message);
+                            let _ = tx.send(Err(api_error.into())).await;
+                        }
+                        return;
+                    }
                     if event.event_type != "content_block_delta" {
                         continue;
                     }
@@ -123,7 +173,9 @@ impl LLMProvider for AnthropicClient {
                         },
                         _ => continue,
                     };
-                    let _ = tx.send(message).await;
+                    if tx.send(Ok(message)).await.is_err() {
+                        return;
+                    }
                 }
             }
         }); 
@@ -132,10 +184,6 @@ impl LLMProvider for AnthropicClient {
     
     async fn get_available_models(&self) -> Result<Vec<String>> {
         // Anthropic doesn't have a public models endpoint, so return known models
-        Ok(vec![
-            "claude-sonnet-4-20250514".to_string(),
-            "claude-opus-4-20250514".to_string(),
-            "claude-3-5-haiku-20241022".to_string(),
-        ])
+        Ok(MODELS.iter().map(|(name, _)| name.to_string()).collect())
     }
 }
\ No newline at end of file
@@ -19,7 +19,7 @@ impl ApiManager {
         })
     }
     
-    pub fn _save_openai_key(&self, key: &str) -> Result<()> {
+    pub fn save_openai_key(&self, key: &str) -> Result<()> {
         self.openai_entry.set_password(key)
             .context("Failed to save OpenAI API key")
     }
@@ -39,7 +39,7 @@ impl ApiManager {
             .context("Failed to retrieve Anthropic API key")
     }
     
-    pub fn _save_google_key(&self, key: &str) -> Result<()> {
+    pub fn save_google_key(&self, key: &str) -> Result<()> {
         self.google_entry.set_password(key)
             .context("Failed to save Google API key")
     }
@@ -48,4 +48,24 @@ impl ApiManager {
         self.google_entry.get_password()
             .context("Failed to retrieve Google API key")
     }
-}
\ No newline at end of file
+    
+    pub fn save_endpoint_key(&self, endpoint: &str, key: &str) -> Result<()> {
+        Self::endpoint_entry(endpoint)?.set_password(key)
+            .with_context(|| format!("Failed to save API key for endpoint {}", endpoint))
+    }
+    
+    pub fn get_endpoint_key(&self, endpoint: &str) -> Result<String> {
+        Self::endpoint_entry(endpoint)?.get_password()
+            .with_context(|| format!("Failed to retrieve API key for endpoint {}", endpoint))
+    }
+    
+    pub fn delete_endpoint_key(&self, endpoint: &str) -> Result<()> {
+        Self::endpoint_entry(endpoint)?.delete_credential()
+            .with_context(|| format!("Failed to delete API key for endpoint {}", endpoint))
+    }
+    
+    fn endpoint_entry(endpoint: &str) -> Result<Entry> {
+        Entry::new("mosaik", &format!("endpoint-{}-api-key", endpoint))
+            .with_context(|| format!("Failed to create keyring entry for endpoint {}", endpoint))
+    }
+}
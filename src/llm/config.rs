@@ -0,0 +1,115 @@
+use serde::{Deserialize, Serialize};
+use crate::components::ProviderType;
+
+/// Provider settings persisted next to the workflows
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProviderConfig {
+    #[serde(default)]
+    pub ollama: OllamaConfig,
+    #[serde(default)]
+    pub endpoints: Vec<EndpointConfig>,
+    #[serde(default)]
+    pub concurrency: ConcurrencyConfig,
+}
+
+/// Where the Ollama server lives and how long it may stay silent
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OllamaConfig {
+    pub host: String,
+    /// Seconds without receiving data before a request is abandoned, 0 disables the timeout
+    pub timeout_secs: u64,
+    /// How long Ollama keeps the model loaded, e.g. "5m", "1h" or "-1"
+    pub keep_alive: Option<String>,
+}
+
+impl OllamaConfig {
+    /// Base URL of the Ollama API
+    pub fn api_base(&self) -> String {
+        self.host.trim().trim_end_matches('/').to_string()
+    }
+}
+
+impl Default for OllamaConfig {
+    fn default() -> Self {
+        Self {
+            host: "http://localhost:11434".to_string(),
+            timeout_secs: 300,
+            keep_alive: None,
+        }
+    }
+}
+
+/// How many generations a workflow run sends at the same time, unset provider limits
+/// only count towards the global one
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConcurrencyConfig {
+    pub max_parallel: usize,
+    pub ollama: Option<usize>,
+    pub anthropic: Option<usize>,
+    pub openai: Option<usize>,
+    pub google: Option<usize>,
+}
+
+impl Default for ConcurrencyConfig {
+    fn default() -> Self {
+        Self {
+            max_parallel: 4,
+            ollama: None,
+            anthropic: None,
+            openai: None,
+            google: None,
+        }
+    }
+}
+
+/// A self-hosted server speaking the OpenAI chat completions API
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct EndpointConfig {
+    pub name: String,
+    pub base_url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_parallel: Option<usize>,
+}
+
+impl ProviderConfig {
+    pub fn endpoint(&self, name: &str) -> Option<&EndpointConfig> {
+        self.endpoints.iter().find(|endpoint| endpoint.name == name)
+    }
+    
+    /// Maximum number of parallel generations for a single provider
+    pub fn provider_limit(&self, provider: &ProviderType) -> Option<usize> {
+        match provider {
+            ProviderType::Ollama => self.concurrency.ollama,
+            ProviderType::Anthropic => self.concurrency.anthropic,
+            ProviderType::OpenAI => self.concurrency.openai,
+            ProviderType::Google => self.concurrency.google,
+            ProviderType::OpenAICompatible { endpoint } => self.endpoint(endpoint).and_then(|e| e.max_parallel),
+        }
+    }
+    
+    /// Every provider that has a limit of its own
+    pub fn limited_providers(&self) -> Vec<(ProviderType, usize)> {
+        let endpoints = self.endpoints
+            .iter()
+            .map(|endpoint| ProviderType::OpenAICompatible { endpoint: endpoint.name.clone() });
+        [ProviderType::Ollama, ProviderType::Anthropic, ProviderType::OpenAI, ProviderType::Google]
+            .into_iter()
+            .chain(endpoints)
+            .filter_map(|provider| self.provider_limit(&provider).map(|limit| (provider, limit)))
+            .collect()
+    }
+}
+
+impl EndpointConfig {
+    /// Base URL of the OpenAI style API, accepts both "http://host:port" and "http://host:port/v1"
+    pub fn api_base(&self) -> String {
+        let base_url = self.base_url.trim().trim_end_matches('/');
+        if base_url.ends_with("/v1") {
+            base_url.to_string()
+        } else {
+            format!("{}/v1", base_url)
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use std::fmt;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+
+/// Broad category of a failed generation, decides how the failure is presented on the node
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    Network,
+    Auth,
+    RateLimit,
+    BadRequest,
+    Other,
+}
+
+impl ErrorKind {
+    pub fn from_status(status: StatusCode) -> Self {
+        match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => ErrorKind::Auth,
+            // Anthropic answers 529 when it is overloaded
+            StatusCode::TOO_MANY_REQUESTS => ErrorKind::RateLimit,
+            status if status.as_u16() == 529 => ErrorKind::RateLimit,
+            status if status.is_client_error() => ErrorKind::BadRequest,
+            _ => ErrorKind::Other,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ErrorKind::Network => "Network error",
+            ErrorKind::Auth => "Authentication error",
+            ErrorKind::RateLimit => "Rate limited",
+            ErrorKind::BadRequest => "Bad request",
+            ErrorKind::Other => "Error",
+        }
+    }
+
+    /// Classify any error returned from a provider, falling back to `Other` for local failures
+    pub fn of(error: &anyhow::Error) -> Self {
+        for cause in error.chain() {
+            if let Some(api_error) = cause.downcast_ref::<ApiError>() {
+                return api_error.kind;
+            }
+            if let Some(request_error) = cause.downcast_ref::<reqwest::Error>() {
+                return match request_error.status() {
+                    Some(status) => ErrorKind::from_status(status),
+                    None => ErrorKind::Network,
+                };
+            }
+        }
+        ErrorKind::Other
+    }
+}
+
+/// Error reported by a provider API, either as an HTTP status or as an event inside the stream
+#[derive(Debug)]
+pub struct ApiError {
+    pub provider: String,
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl ApiError {
+    pub fn new(provider: &str, kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            provider: provider.to_string(),
+            kind,
+            message: message.into(),
+        }
+    }
+
+    pub fn missing_key(provider: &str) -> Self {
+        Self::new(provider, ErrorKind::Auth, "API key not configured")
+    }
+
+    /// Build the error for a non-success response, preferring the message from a JSON error body
+    pub fn from_response(provider: &str, status: StatusCode, body: &str) -> Self {
+        let message = serde_json::from_str::<serde_json::Value>(body)
+            .ok()
+            .and_then(|json| {
+                let error = json.get("error")?;
+                error.get("message")
+                    .and_then(|message| message.as_str())
+                    .or_else(|| error.as_str())
+                    .map(|message| message.to_string())
+            })
+            .unwrap_or_else(|| body.to_string());
+        Self::new(provider, ErrorKind::from_status(status), format!("Status {}: {}", status, message))
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} API error: {}", self.provider, self.message)
+    }
+}
+
+impl std::error::Error for ApiError {}
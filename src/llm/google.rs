@@ -0,0 +1,248 @@
+use dioxus::logger::tracing::{info, error};
+use serde::{Deserialize, Serialize};
+use anyhow::{Result, Context};
+use crate::{
+    llm::{ApiManager, ApiError},
+    components::{ChatMessage, MessageRole, ModelOptions}
+};
+use super::{LLMProvider, next_chunk};
+
+// Client for interacting with the Gemini generateContent API
+pub struct GeminiClient {
+    client: reqwest::Client,
+    base_url: String,
+    default_model: String,
+    api_key: Option<String>,
+}
+
+// Request structure for Gemini's streamGenerateContent API
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiRequest {
+    contents: Vec<GeminiContent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system_instruction: Option<GeminiContent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    generation_config: Option<GeminiGenerationConfig>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiGenerationConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_output_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    stop_sequences: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    thinking_config: Option<GeminiThinkingConfig>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiThinkingConfig {
+    include_thoughts: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct GeminiContent {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+    #[serde(default)]
+    pub parts: Vec<GeminiPart>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GeminiPart {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thought: Option<bool>,
+}
+
+// Streaming chunk from the streamGenerateContent API
+#[derive(Deserialize, Debug)]
+pub struct GeminiResponse {
+    #[serde(default)]
+    pub candidates: Vec<GeminiCandidate>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct GeminiCandidate {
+    #[serde(default)]
+    pub content: GeminiContent,
+}
+
+impl GeminiClient {
+    /// Convert chat messages to Gemini contents, assistant turns use the "model" role
+    /// and system messages are split off into the system instruction
+    fn to_contents(messages: Vec<ChatMessage>) -> (Vec<GeminiContent>, Option<GeminiContent>) {
+        let (system_messages, messages): (Vec<ChatMessage>, Vec<ChatMessage>) = messages
+            .into_iter()
+            .filter(|message| !message.content.is_empty())
+            .partition(|message| message.role == MessageRole::System);
+        
+        let contents = messages
+            .into_iter()
+            .map(|message| GeminiContent {
+                role: Some(match message.role {
+                    MessageRole::Assistant => "model".to_string(),
+                    _ => "user".to_string(),
+                }),
+                parts: vec![GeminiPart { text: Some(message.content), thought: None }],
+            })
+            .collect();
+        let system_instruction = (!system_messages.is_empty()).then(|| GeminiContent {
+            role: None,
+            parts: system_messages
+                .into_iter()
+                .map(|message| GeminiPart { text: Some(message.content), thought: None })
+                .collect(),
+        });
+        (contents, system_instruction)
+    }
+}
+
+impl LLMProvider for GeminiClient {
+    fn new() -> Self {
+        let api_key = ApiManager::new()
+            .ok()
+            .and_then(|key_manager| key_manager.get_google_key().ok())
+            .or_else(|| std::env::var("GEMINI_API_KEY").ok());
+        Self {
+            client: reqwest::Client::new(),
+            base_url: "https://generativelanguage.googleapis.com/v1beta".to_string(),
+            default_model: "gemini-2.5-flash".to_string(),
+            api_key,
+        }
+    }
+
+    async fn generate(&self, model: Option<String>, messages: Vec<ChatMessage>, thinking: Option<bool>, options: ModelOptions) -> Result<tokio::sync::mpsc::Receiver<Result<ChatMessage>>> {
+        let model = model
+            .filter(|m| !m.is_empty())
+            .unwrap_or_else(|| self.default_model.clone());
+        let url = format!("{}/models/{}:streamGenerateContent?alt=sse", self.base_url, model);
+
+        let thinking_config = match thinking {
+            Some(true) => Some(GeminiThinkingConfig { include_thoughts: true }),
+            _ => None
+        };
+        let (contents, system_instruction) = Self::to_contents(messages);
+        let request = GeminiRequest {
+            contents,
+            system_instruction,
+            generation_config: Some(GeminiGenerationConfig {
+                temperature: options.temperature,
+                top_p: options.top_p,
+                top_k: options.top_k,
+                max_output_tokens: options.max_tokens,
+                stop_sequences: options.stop,
+                seed: options.seed,
+                thinking_config,
+            }),
+        };
+
+        info!("Sending request to Gemini API");
+
+        let api_key = self.api_key.as_ref()
+            .ok_or_else(|| ApiError::missing_key("Gemini"))?;
+        let mut response = self.client
+            .post(&url)
+            .header("x-goog-api-key", api_key)
+            .json(&request)
+            .send()
+            .await
+            .context("Failed to send request to Gemini API")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await
+                .unwrap_or_else(|_| "Unable to read error response".to_string());
+            error!("Gemini API returned error: Status {}, Content: {}", status, error_text);
+            return Err(ApiError::from_response("Gemini", status, &error_text).into());
+        }
+
+        let (tx, rx) = tokio::sync::mpsc::channel::<Result<ChatMessage>>(100);
+        tokio::spawn(async move {
+            let mut buffer = String::new();
+
+            while let Some(bytes) = next_chunk(&mut response, &tx).await {
+                buffer.push_str(&String::from_utf8_lossy(&bytes));
+
+                while let Some(line_end) = buffer.find('\n') {
+                    let line = buffer[..line_end].trim().to_string();
+                    buffer.drain(..=line_end);
+
+                    let Some(json_part) = line.strip_prefix("data:") else {
+                        continue;
+                    };
+                    let Ok(chunk) = serde_json::from_str::<GeminiResponse>(json_part.trim()) else {
+                        continue;
+                    };
+
+                    let parts = chunk.candidates
+                        .into_iter()
+                        .flat_map(|candidate| candidate.content.parts);
+                    for part in parts {
+                        let Some(text) = part.text else { continue; };
+                        if text.is_empty() { continue; }
+                        let message = if part.thought == Some(true) {
+                            ChatMessage {
+                                role: MessageRole::Assistant,
+                                content: String::new(),
+                                thinking: Some(text)
+                            }
+                        } else {
+                            ChatMessage {
+                                role: MessageRole::Assistant,
+                                content: text,
+                                thinking: None
+                            }
+                        };
+                        if tx.send(Ok(message)).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+        });
+        Ok(rx)
+    }
+
+    async fn get_available_models(&self) -> Result<Vec<String>> {
+        let url = format!("{}/models?pageSize=1000", self.base_url);
+
+        let api_key = self.api_key.as_ref()
+            .ok_or_else(|| ApiError::missing_key("Gemini"))?;
+        let response = self.client
+            .get(&url)
+            .header("x-goog-api-key", api_key)
+            .send()
+            .await
+            .context("Failed to fetch Gemini models")?;
+
+        // Model names come back as "models/<id>", only keep the ones that can generate content
+        let models: serde_json::Value = response.json().await?;
+        let model_names = models["models"]
+            .as_array()
+            .unwrap_or(&vec![])
+            .iter()
+            .filter(|m| {
+                m["supportedGenerationMethods"]
+                    .as_array()
+                    .is_some_and(|methods| methods.iter().any(|method| method == "generateContent"))
+            })
+            .filter_map(|m| m["name"].as_str())
+            .map(|s| s.trim_start_matches("models/").to_string())
+            .collect();
+
+        Ok(model_names)
+    }
+}
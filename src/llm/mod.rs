@@ -1,29 +1,95 @@
 use serde::{Deserialize, Serialize};
 use anyhow::Result;
-use crate::components::ChatMessage;
+use crate::{
+    components::{ChatMessage, ModelOptions, ProviderType},
+    file_manager
+};
 
 mod ollama;
 mod anthropic;
+mod openai;
+mod google;
 mod api_manager;
+mod config;
+mod error;
+#[cfg(test)]
+pub mod test_server;
 
 pub use {
     ollama::OllamaClient,
     anthropic::AnthropicClient,
-    api_manager::ApiManager
+    openai::OpenAIClient,
+    google::GeminiClient,
+    api_manager::ApiManager,
+    config::{ProviderConfig, EndpointConfig, ConcurrencyConfig},
+    error::{ApiError, ErrorKind}
 }; 
 
-// Request structure for Ollama's generate API
-#[derive(Serialize)]
+// Request structure shared by the Ollama, Anthropic and OpenAI chat APIs
+#[derive(Serialize, Default)]
 pub struct LLMRequest {
     model: String,
     messages: Vec<ChatMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<String>,
     stream: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     max_tokens: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    max_completion_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_sequences: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     think: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     thinking: Option<ThinkingConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    options: Option<OllamaOptions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keep_alive: Option<String>,
+}
+
+// Ollama takes sampling parameters in a nested options object
+#[derive(Serialize, Default, PartialEq)]
+pub struct OllamaOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_ctx: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_predict: Option<u32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    stop: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<i64>,
+}
+
+impl From<ModelOptions> for OllamaOptions {
+    fn from(options: ModelOptions) -> Self {
+        Self {
+            num_ctx: options.num_ctx,
+            temperature: options.temperature,
+            top_p: options.top_p,
+            top_k: options.top_k,
+            num_predict: options.max_tokens,
+            stop: options.stop,
+            seed: options.seed,
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -39,6 +105,27 @@ pub struct LLMResponse {
     pub event_type: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub delta: Option<ContentDelta>,
+    #[serde(default)]
+    pub error: Option<StreamError>,
+}
+
+// Error event sent inside an Anthropic stream, e.g. when the API becomes overloaded mid-response
+#[derive(Deserialize, Debug)]
+pub struct StreamError {
+    #[serde(rename = "type")]
+    pub error_type: String,
+    pub message: String,
+}
+
+impl StreamError {
+    pub fn kind(&self) -> ErrorKind {
+        match self.error_type.as_str() {
+            "authentication_error" | "permission_error" => ErrorKind::Auth,
+            "rate_limit_error" | "overloaded_error" => ErrorKind::RateLimit,
+            "invalid_request_error" | "not_found_error" | "request_too_large" => ErrorKind::BadRequest,
+            _ => ErrorKind::Other,
+        }
+    }
 }
 
 #[derive(Deserialize, Debug)]
@@ -51,8 +138,73 @@ pub struct ContentDelta {
     pub thinking: Option<String>,
 }
 
+/// Read the next chunk of a streaming response, giving up as soon as the receiver is dropped
+/// so a stopped generation also closes the HTTP connection. A dropped connection is passed on
+/// to the receiver instead of silently ending the stream
+async fn next_chunk(response: &mut reqwest::Response, tx: &tokio::sync::mpsc::Sender<Result<ChatMessage>>) -> Option<Vec<u8>> {
+    let chunk = tokio::select! {
+        chunk = response.chunk() => chunk,
+        _ = tx.closed() => return None,
+    };
+    match chunk {
+        Ok(bytes) => bytes.map(|bytes| bytes.to_vec()),
+        Err(e) => {
+            let _ = tx.send(Err(anyhow::Error::new(e).context("Connection lost while streaming"))).await;
+            None
+        }
+    }
+}
+
 pub trait LLMProvider {
     fn new() -> Self;
-    async fn generate(&self, model: Option<String>, messages: Vec<ChatMessage>, thinking: Option<bool>) -> Result<tokio::sync::mpsc::Receiver<ChatMessage>>;
+    async fn generate(&self, model: Option<String>, messages: Vec<ChatMessage>, thinking: Option<bool>, options: ModelOptions) -> Result<tokio::sync::mpsc::Receiver<Result<ChatMessage>>>;
     async fn get_available_models(&self) -> Result<Vec<String>>;
-}
\ No newline at end of file
+}
+
+/// Start a streaming generation with the client for the given provider
+/// The server a provider's requests go to when it can be configured, `None` for the fixed APIs
+pub fn configured_server(provider: &ProviderType) -> Option<String> {
+    match provider {
+        ProviderType::Ollama => Some(file_manager::load_provider_config().unwrap_or_default().ollama.api_base()),
+        ProviderType::OpenAI => Some(OpenAIClient::api_base()),
+        ProviderType::OpenAICompatible { endpoint } => file_manager::load_provider_config()
+            .unwrap_or_default()
+            .endpoint(endpoint)
+            .map(EndpointConfig::api_base),
+        ProviderType::Anthropic | ProviderType::Google => None,
+    }
+}
+
+pub async fn generate(
+    provider: ProviderType,
+    model_name: Option<String>,
+    messages: Vec<ChatMessage>,
+    thinking: Option<bool>,
+    options: ModelOptions
+) -> Result<tokio::sync::mpsc::Receiver<Result<ChatMessage>>> {
+    match provider {
+        ProviderType::Ollama => {
+            let client = OllamaClient::new();
+            client.generate(model_name, messages, thinking, options).await
+        },
+        ProviderType::Anthropic => {
+            let client = AnthropicClient::new();
+            client.generate(model_name, messages, thinking, options).await
+        },
+        ProviderType::OpenAI => {
+            let client = OpenAIClient::new();
+            client.generate(model_name, messages, thinking, options).await
+        },
+        ProviderType::Google => {
+            let client = GeminiClient::new();
+            client.generate(model_name, messages, thinking, options).await
+        },
+        ProviderType::OpenAICompatible { endpoint } => {
+            let config = file_manager::load_provider_config().unwrap_or_default();
+            let endpoint_config = config.endpoint(&endpoint)
+                .ok_or_else(|| anyhow::anyhow!("Endpoint {} is not configured", endpoint))?;
+            let client = OpenAIClient::for_endpoint(endpoint_config);
+            client.generate(model_name, messages, thinking, options).await
+        }
+    }
+}
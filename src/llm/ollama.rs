@@ -1,14 +1,19 @@
 use dioxus::logger::tracing::{info, error};
 use serde::Deserialize;
 use anyhow::{Result, Context};
-use crate::components::ChatMessage;
-use super::{LLMRequest, LLMProvider};
+use std::time::Duration;
+use crate::{
+    components::{ChatMessage, ModelOptions},
+    file_manager
+};
+use super::{LLMRequest, LLMProvider, OllamaOptions, ApiError, ErrorKind, next_chunk};
 
 // Client for interacting with the Ollama API
 pub struct OllamaClient {
     client: reqwest::Client,
     base_url: String,
-    default_model: String
+    default_model: String,
+    keep_alive: Option<String>
 }
 
 // Response structure from Ollama's generate API
@@ -19,27 +24,36 @@ pub struct OllamaResponse {
 }
 
 impl LLMProvider for OllamaClient {
-    /// Create a new Ollama client
+    /// Create a new Ollama client from the saved provider configuration
     fn new() -> Self {
+        let config = file_manager::load_provider_config().unwrap_or_default().ollama;
+        let mut builder = reqwest::Client::builder();
+        if config.timeout_secs > 0 {
+            // Streams can run for minutes, so time out on silence rather than total duration
+            builder = builder.read_timeout(Duration::from_secs(config.timeout_secs));
+        }
         Self {
-            client: reqwest::Client::new(),
-            base_url: "http://localhost:11434".to_string(),
-            default_model: "".to_string()
+            client: builder.build().unwrap_or_default(),
+            base_url: config.api_base(),
+            default_model: "".to_string(),
+            keep_alive: config.keep_alive.filter(|keep_alive| !keep_alive.trim().is_empty())
         }
     }
 
     /// Generate text using the Ollama API
-    async fn generate(&self, model: Option<String>, messages: Vec<ChatMessage>, think: Option<bool>) -> Result<tokio::sync::mpsc::Receiver<ChatMessage>> {
+    async fn generate(&self, model: Option<String>, messages: Vec<ChatMessage>, think: Option<bool>, options: ModelOptions) -> Result<tokio::sync::mpsc::Receiver<Result<ChatMessage>>> {
         let model = model.unwrap_or_else(|| self.default_model.clone());
         let url = format!("{}/api/chat", self.base_url);
         
+        let options = OllamaOptions::from(options);
         let request = LLMRequest {
             model,
             messages,
             stream: true,
-            max_tokens: None,
             think,
-            thinking: None
+            options: (options != OllamaOptions::default()).then_some(options),
+            keep_alive: self.keep_alive.clone(),
+            ..Default::default()
         };
 
         info!("Sending request to Ollama API at {}", url);
@@ -56,14 +70,14 @@ impl LLMProvider for OllamaClient {
             let error_text = response.text().await
                 .unwrap_or_else(|_| "Unable to read error response".to_string());
             error!("Ollama API returned error: Status {}, Content: {}", status, error_text);
-            anyhow::bail!("Ollama API error: Status {}: {}", status, error_text);
+            return Err(ApiError::from_response("Ollama", status, &error_text).into());
         }
 
-        let (tx, rx) = tokio::sync::mpsc::channel::<ChatMessage>(100);
+        let (tx, rx) = tokio::sync::mpsc::channel::<Result<ChatMessage>>(100);
         tokio::spawn(async move {
             let mut buffer = String::new();
             
-            while let Ok(Some(bytes)) = response.chunk().await {
+            while let Some(bytes) = next_chunk(&mut response, &tx).await {
                 buffer.push_str(&String::from_utf8_lossy(&bytes));
                 
                 while let Some(line_end) = buffer.find('\n') {
@@ -75,11 +89,21 @@ impl LLMProvider for OllamaClient {
                     }
                     
                     let Ok(response_chunk) = serde_json::from_str::<OllamaResponse>(&line) else {
+                        // Failures after the stream has started arrive as {"error": "..."}
+                        let stream_error = serde_json::from_str::<serde_json::Value>(&line)
+                            .ok()
+                            .and_then(|json| json["error"].as_str().map(|message| message.to_string()));
+                        if let Some(message) = stream_error {
+                            error!("Ollama stream returned error: {}", message);
+                            let _ = tx.send(Err(ApiError::new("Ollama", ErrorKind::Other, message).into())).await;
+                            return;
+                        }
                         continue;
                     };
                     
-                    if !response_chunk.message.content.is_empty() || response_chunk.message.thinking.is_some() {
-                        let _ = tx.send(response_chunk.message).await;
+                    let has_content = !response_chunk.message.content.is_empty() || response_chunk.message.thinking.is_some();
+                    if has_content && tx.send(Ok(response_chunk.message)).await.is_err() {
+                        return;
                     }
                     
                     if response_chunk.done {
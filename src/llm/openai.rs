@@ -0,0 +1,287 @@
+use dioxus::logger::tracing::{info, error};
+use serde::Deserialize;
+use anyhow::{Result, Context};
+use crate::{
+    llm::{ApiManager, ApiError, EndpointConfig},
+    components::{ChatMessage, MessageRole, ModelOptions}
+};
+use super::{LLMRequest, LLMProvider, next_chunk};
+
+// Client for the OpenAI chat completions API and servers compatible with it
+pub struct OpenAIClient {
+    client: reqwest::Client,
+    name: String,
+    base_url: String,
+    default_model: String,
+    api_key: Option<String>,
+    chat_models_only: bool,
+}
+
+// Streaming chunk from the chat completions API
+#[derive(Deserialize, Debug)]
+pub struct OpenAIStreamChunk {
+    #[serde(default)]
+    pub choices: Vec<OpenAIChoice>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct OpenAIChoice {
+    pub delta: OpenAIDelta,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct OpenAIDelta {
+    #[serde(default)]
+    pub content: Option<String>,
+    #[serde(default)]
+    pub reasoning_content: Option<String>,
+}
+
+impl OpenAIClient {
+    /// Create a client for a user configured OpenAI-compatible endpoint
+    pub fn for_endpoint(endpoint: &EndpointConfig) -> Self {
+        let api_key = if let Ok(key_manager) = ApiManager::new() {
+            key_manager.get_endpoint_key(&endpoint.name).ok()
+        } else {
+            None
+        };
+        Self {
+            client: reqwest::Client::new(),
+            name: endpoint.name.clone(),
+            base_url: endpoint.api_base(),
+            default_model: "".to_string(),
+            api_key,
+            chat_models_only: false,
+        }
+    }
+
+    /// Base URL of the OpenAI API, `OPENAI_BASE_URL` overrides it
+    pub fn api_base() -> String {
+        std::env::var("OPENAI_BASE_URL")
+            .unwrap_or_else(|_| "https://api.openai.com/v1".to_string())
+            .trim_end_matches('/')
+            .to_string()
+    }
+
+    pub fn with_api_key(mut self, api_key: Option<String>) -> Self {
+        self.api_key = api_key;
+        self
+    }
+
+    fn authorize(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.api_key {
+            Some(api_key) => request.bearer_auth(api_key),
+            None => request,
+        }
+    }
+}
+
+impl LLMProvider for OpenAIClient {
+    /// Create a new OpenAI client, `OPENAI_BASE_URL` overrides the API location and
+    /// `OPENAI_API_KEY` is used when no key is stored in the keyring
+    fn new() -> Self {
+        let api_key = ApiManager::new()
+            .ok()
+            .and_then(|key_manager| key_manager.get_openai_key().ok())
+            .or_else(|| std::env::var("OPENAI_API_KEY").ok());
+        Self {
+            client: reqwest::Client::new(),
+            name: "OpenAI".to_string(),
+            base_url: Self::api_base(),
+            default_model: "gpt-4.1".to_string(),
+            api_key,
+            chat_models_only: true,
+        }
+    }
+
+    /// Generate text using the streaming chat completions API
+    async fn generate(&self, model: Option<String>, messages: Vec<ChatMessage>, _thinking: Option<bool>, options: ModelOptions) -> Result<tokio::sync::mpsc::Receiver<Result<ChatMessage>>> {
+        let model = model.unwrap_or_else(|| self.default_model.clone());
+        let url = format!("{}/chat/completions", self.base_url);
+
+        // OpenAI's reasoning models only accept max_completion_tokens, compatible servers expect max_tokens
+        let (max_tokens, max_completion_tokens) = if self.chat_models_only {
+            (None, options.max_tokens)
+        } else {
+            (options.max_tokens, None)
+        };
+        let request = LLMRequest {
+            model,
+            messages,
+            stream: true,
+            max_tokens,
+            max_completion_tokens,
+            temperature: options.temperature,
+            top_p: options.top_p,
+            top_k: options.top_k.filter(|_| !self.chat_models_only),
+            stop: (!options.stop.is_empty()).then_some(options.stop),
+            seed: options.seed,
+            ..Default::default()
+        };
+
+        info!("Sending request to {} API at {}", self.name, url);
+
+        let mut response = self.authorize(self.client.post(&url))
+            .json(&request)
+            .send()
+            .await
+            .with_context(|| format!("Failed to send request to {} API", self.name))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await
+                .unwrap_or_else(|_| "Unable to read error response".to_string());
+            error!("{} API returned error: Status {}, Content: {}", self.name, status, error_text);
+            return Err(ApiError::from_response(&self.name, status, &error_text).into());
+        }
+
+        let (tx, rx) = tokio::sync::mpsc::channel::<Result<ChatMessage>>(100);
+        tokio::spawn(async move {
+            let mut buffer = String::new();
+
+            while let Some(bytes) = next_chunk(&mut response, &tx).await {
+                buffer.push_str(&String::from_utf8_lossy(&bytes));
+
+                while let Some(line_end) = buffer.find('\n') {
+                    let line = buffer[..line_end].trim().to_string();
+                    buffer.drain(..=line_end);
+
+                    let Some(json_part) = line.strip_prefix("data:") else {
+                        continue;
+                    };
+                    let json_part = json_part.trim();
+                    if json_part == "[DONE]" {
+                        return;
+                    }
+
+                    let Ok(chunk) = serde_json::from_str::<OpenAIStreamChunk>(json_part) else {
+                        continue;
+                    };
+
+                    for choice in chunk.choices {
+                        let content = choice.delta.content.unwrap_or_default();
+                        let thinking = choice.delta.reasoning_content.filter(|t| !t.is_empty());
+                        if content.is_empty() && thinking.is_none() {
+                            continue;
+                        }
+                        let message = ChatMessage {
+                            role: MessageRole::Assistant,
+                            content,
+                            thinking
+                        };
+                        if tx.send(Ok(message)).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+        });
+        Ok(rx)
+    }
+
+    async fn get_available_models(&self) -> Result<Vec<String>> {
+        let url = format!("{}/models", self.base_url);
+
+        let response = self.authorize(self.client.get(&url))
+            .send()
+            .await
+            .with_context(|| format!("Failed to fetch {} models", self.name))?
+            .error_for_status()?;
+
+        let models: serde_json::Value = response.json().await?;
+        let mut model_names: Vec<String> = models["data"]
+            .as_array()
+            .unwrap_or(&vec![])
+            .iter()
+            .filter_map(|m| m["id"].as_str())
+            .map(|s| s.to_string())
+            .collect();
+        
+        // OpenAI also lists embedding, audio and image models, keep the chat ones
+        if self.chat_models_only {
+            model_names.retain(|id| is_chat_model(id));
+        }
+        model_names.sort();
+
+        Ok(model_names)
+    }
+}
+
+/// Whether an OpenAI model id names a GPT or o-series reasoning model that chat completions accept
+fn is_chat_model(id: &str) -> bool {
+    let reasoning = ["o1", "o3", "o4"]
+        .iter()
+        .any(|family| id == *family || id.strip_prefix(family).is_some_and(|rest| rest.starts_with('-')));
+    let chat = id.starts_with("gpt-") || id.starts_with("chatgpt-") || reasoning;
+    chat && !["audio", "realtime", "transcribe", "tts", "image"].iter().any(|kind| id.contains(kind))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::test_server::{serve_openai, Response};
+
+    fn user_message(content: &str) -> Vec<ChatMessage> {
+        vec![ChatMessage { role: MessageRole::User, content: content.to_string(), thinking: None }]
+    }
+
+    #[tokio::test]
+    async fn streams_deltas_until_done() {
+        let body = [
+            r#"data: {"choices":[{"delta":{"role":"assistant"}}]}"#,
+            "",
+            r#"data: {"choices":[{"delta":{"reasoning_content":"Thinking"}}]}"#,
+            "",
+            r#"data: {"choices":[{"delta":{"content":"Hel"}}]}"#,
+            ": keep-alive comment",
+            r#"data: {"choices":[{"delta":{"content":"lo"}}]}"#,
+            "",
+            "data: [DONE]",
+            "",
+            r#"data: {"choices":[{"delta":{"content":" after done"}}]}"#,
+            "",
+        ].join("\n");
+        let _server = serve_openai(move |_| Response::events(body.clone())).await;
+        let client = OpenAIClient::new();
+
+        let mut receiver = client.generate(Some("gpt-4.1".to_string()), user_message("Hi"), None, ModelOptions::default())
+            .await
+            .unwrap();
+        let mut content = String::new();
+        let mut thinking = String::new();
+        while let Some(chunk) = receiver.recv().await {
+            let chunk = chunk.unwrap();
+            content.push_str(&chunk.content);
+            thinking.push_str(chunk.thinking.as_deref().unwrap_or_default());
+        }
+        assert_eq!(content, "Hello");
+        assert_eq!(thinking, "Thinking");
+    }
+
+    #[tokio::test]
+    async fn reports_api_errors() {
+        let _server = serve_openai(|_| Response {
+            status: "401 Unauthorized",
+            content_type: "application/json",
+            body: r#"{"error":{"message":"Incorrect API key provided","type":"invalid_request_error"}}"#.to_string(),
+        }).await;
+        let client = OpenAIClient::new();
+
+        let error = client.generate(Some("gpt-4.1".to_string()), user_message("Hi"), None, ModelOptions::default())
+            .await
+            .unwrap_err();
+        let error = error.downcast_ref::<ApiError>().expect("an API error");
+        assert!(error.to_string().contains("Incorrect API key provided"), "{}", error);
+        assert!(error.to_string().contains("401"), "{}", error);
+    }
+
+    #[test]
+    fn lists_only_chat_models() {
+        for id in ["gpt-4.1", "chatgpt-4o-latest", "o1", "o3-mini", "o4-mini-2025-04-16"] {
+            assert!(is_chat_model(id), "{}", id);
+        }
+        for id in ["omni-moderation-latest", "gpt-image-1", "gpt-4o-audio-preview", "o1x", "text-embedding-3-small"] {
+            assert!(!is_chat_model(id), "{}", id);
+        }
+    }
+}
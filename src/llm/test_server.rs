@@ -0,0 +1,83 @@
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{Mutex, MutexGuard};
+
+/// `OPENAI_BASE_URL` is shared by the whole test process, only one test can point it at its server
+static OPENAI_BASE_URL: Mutex<()> = Mutex::const_new(());
+
+/// What the server answers a request with
+pub struct Response {
+    pub status: &'static str,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl Response {
+    /// A chat completions stream with one delta per piece of `content`
+    pub fn stream(content: &[&str]) -> Self {
+        let mut body: String = content
+            .iter()
+            .map(|piece| format!("data: {}\n\n", serde_json::json!({ "choices": [{ "delta": { "content": piece } }] })))
+            .collect();
+        body.push_str("data: [DONE]\n\n");
+        Self::events(body)
+    }
+
+    /// A stream with the given server-sent events as they are
+    pub fn events(body: String) -> Self {
+        Self { status: "200 OK", content_type: "text/event-stream", body }
+    }
+}
+
+/// Point the OpenAI client at a local server that answers every request with `respond`, which gets
+/// the request's JSON body. Other tests wait for the server until the returned guard is dropped
+pub async fn serve_openai(respond: impl Fn(&serde_json::Value) -> Response + Send + Sync + 'static) -> MutexGuard<'static, ()> {
+    let guard = OPENAI_BASE_URL.lock().await;
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    std::env::set_var("OPENAI_BASE_URL", format!("http://{}/v1", listener.local_addr().unwrap()));
+    // Used when the keyring has no key
+    std::env::set_var("OPENAI_API_KEY", "test-key");
+
+    let respond = Arc::new(respond);
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let respond = respond.clone();
+            tokio::spawn(async move {
+                let Some(request) = read_request(&mut socket).await else { return };
+                let response = respond(&request);
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    response.status, response.content_type, response.body.len(), response.body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            });
+        }
+    });
+    guard
+}
+
+/// The JSON body of a request, `Null` for requests without one
+async fn read_request(socket: &mut TcpStream) -> Option<serde_json::Value> {
+    let mut request = Vec::new();
+    let mut buffer = [0; 4096];
+    loop {
+        if let Some(header_end) = request.windows(4).position(|window| window == b"\r\n\r\n") {
+            let headers = String::from_utf8_lossy(&request[..header_end]).to_lowercase();
+            let length: usize = headers
+                .lines()
+                .find_map(|line| line.strip_prefix("content-length:"))
+                .and_then(|length| length.trim().parse().ok())
+                .unwrap_or(0);
+            let body = header_end + 4;
+            if request.len() >= body + length {
+                return Some(serde_json::from_slice(&request[body..body + length]).unwrap_or_default());
+            }
+        }
+        let read = socket.read(&mut buffer).await.ok()?;
+        if read == 0 {
+            return None;
+        }
+        request.extend_from_slice(&buffer[..read]);
+    }
+}
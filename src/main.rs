@@ -2,17 +2,25 @@ use dioxus::prelude::*;
 use dioxus::desktop::{Config, WindowBuilder};
 
 mod llm;
+mod cli;
 mod components;
+mod executor;
 mod file_manager;
 
 use components::{SettingsPopup, Canvas, CanvasState, 
-    WorkflowManager, Workflow, NodeType, execute_model_node
+    WorkflowManager, Workflow, RunScope, ValidationIssue, ValidationReport, start_workflow_run
 };
 
 
 const MAIN_CSS: Asset = asset!("/assets/main.css");
 
 fn main() {
+    // `mosaik run <workflow.json>` executes a saved workflow without opening the window
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|arg| arg == "run") {
+        std::process::exit(cli::run(&args[1..]));
+    }
+    
     #[cfg(feature = "desktop")]
     dioxus::LaunchBuilder::desktop() // From dioxus::desktop::LaunchBuilder
         .with_cfg(
@@ -47,42 +55,16 @@ fn App() -> Element {
     
     let popup_open = use_signal(|| false);
     
-    let run_workflow = move || {
-        spawn(async move {
-            let execution_order = workflow_state.read().execution_order();
-            
-            for node_id in execution_order {
-                let (provider_type, model_name_clone, thinking_enabled, ollama_messages) = {
-                    let mut state = workflow_state.write();
-                    let Some(node_to_update) = state.nodes.get_mut(&node_id) else { continue };
-                    
-                    let NodeType::Model { provider, model_name, thinking, .. } = &mut node_to_update.node_type else { continue };
-                    
-                    let provider_type = provider.clone();
-                    let model_name_clone = model_name.clone();
-                    let thinking_enabled = *thinking;
-                    node_to_update.is_executing = true;
-                    
-                    match node_to_update.prepare_prompt() {
-                        Ok(ollama_messages) => (provider_type, model_name_clone, thinking_enabled, ollama_messages),
-                        Err(e) => {
-                            println!("Failed to prepare prompt for node {}: {}", node_id, e);
-                            node_to_update.is_executing = false;
-                            continue;
-                        }
-                    }
-                };
-                
-                execute_model_node(
-                    workflow_state,
-                    node_id,
-                    ollama_messages,
-                    provider_type,
-                    Some(model_name_clone),
-                    Some(thinking_enabled)
-                ).await;
-            }
-        });
+    let mut validation_issues = use_signal(Vec::<ValidationIssue>::new);
+    
+    // Problems are shown first, the run only starts right away when there are none
+    let mut run_workflow = move || {
+        let issues = workflow_state.read().validate();
+        if issues.is_empty() {
+            start_workflow_run(workflow_state, RunScope::All);
+        } else {
+            validation_issues.set(issues);
+        }
     };
     
     rsx! {
@@ -95,6 +77,9 @@ fn App() -> Element {
                 if event.key() == Key::Enter && event.modifiers().ctrl() {
                     event.prevent_default();
                     run_workflow();
+                } else if event.key() == Key::Escape && workflow_state.read().is_running() {
+                    event.prevent_default();
+                    workflow_state.write().stop_all();
                 }
             },
             Toolbar {
@@ -102,6 +87,17 @@ fn App() -> Element {
                 popup_open,
                 run_workflow
             }
+            if !validation_issues.read().is_empty() {
+                ValidationReport {
+                    issues: validation_issues(),
+                    workflow_state,
+                    on_close: move |_| validation_issues.set(Vec::new()),
+                    on_run: move |_| {
+                        validation_issues.set(Vec::new());
+                        start_workflow_run(workflow_state, RunScope::All);
+                    }
+                }
+            }
             
             // Canvas container with our Canvas component
             div {
@@ -127,6 +123,7 @@ fn Toolbar(
     popup_open: Signal<bool>,
     run_workflow: EventHandler<()>,
 ) -> Element {
+    let mut save_error = use_signal(|| None::<String>);
     let mut toggle_panel = move || {
         popup_open.set(!popup_open());
     };
@@ -136,32 +133,45 @@ fn Toolbar(
             class: "toolbar",
             style: "position: absolute; top: 10px; right: 10px; z-index: 100; display: flex; gap: 2px;",
             
-            button {
-                class: "run-button",
-                style: "padding: 8px 16px; background: transparent; color: var(--text-primary); 
-                    border: none; cursor: pointer; font-weight: bold;
-                    display: flex; flex-direction: column; align-items: center; gap: 2px;",
-                onclick: move |_| run_workflow(()),
-                div { "Run" }
-                div { 
-                    style: "font-size: 10px; font-weight: normal;",
-                    "( Ctrl + ⏎ )"
+            if workflow_state.read().is_running() {
+                button {
+                    class: "stop-button",
+                    style: "padding: 8px 16px; background: transparent; color: var(--text-error); 
+                        border: none; cursor: pointer; font-weight: bold;
+                        display: flex; flex-direction: column; align-items: center; gap: 2px;",
+                    onclick: move |_| workflow_state.write().stop_all(),
+                    div { "Stop" }
+                    div { 
+                        style: "font-size: 10px; font-weight: normal;",
+                        "( Esc )"
+                    }
+                }
+            } else {
+                button {
+                    class: "run-button",
+                    style: "padding: 8px 16px; background: transparent; color: var(--text-primary); 
+                        border: none; cursor: pointer; font-weight: bold;
+                        display: flex; flex-direction: column; align-items: center; gap: 2px;",
+                    onclick: move |_| run_workflow(()),
+                    div { "Run" }
+                    div { 
+                        style: "font-size: 10px; font-weight: normal;",
+                        "( Ctrl + ⏎ )"
+                    }
                 }
             }
             
             button {
                 class: "save-button",
-                style: "padding: 8px 16px; background: transparent; color: var(--text-primary); 
+                style: format!("padding: 8px 16px; background: transparent; color: {}; 
                     border: none; cursor: pointer;",
+                    if save_error().is_some() { "var(--text-error)" } else { "var(--text-primary)" }),
+                title: save_error().unwrap_or_default(),
                 onclick: move |_| {
-                    let workflow = workflow_state.read();
-                    if let Err(e) = file_manager::save_default_workflow(&workflow) {
-                        println!("Failed to save workflow: {}", e);
-                    } else {
-                        println!("Workflow saved successfully");
-                    }
+                    let result = file_manager::save_default_workflow(&workflow_state.read());
+                    save_error.set(result.err().map(|e| format!("Failed to save workflow: {:#}", e)));
                 },
-                "Save"
+                if save_error().is_some() { "Save failed" } else { "Save" }
             }
             
             // button {